@@ -0,0 +1,154 @@
+use chronobase::EntityID;
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::{Monster, Team}, events::Event, game::{ActiveCharacter, ActiveCharacterID, Game, IdSource, Sim}, modifiers::ModifierType, progress_bars::{GameProgressBarLocation, ProgressBarName}, statuses::Status, summons::SummonKind};
+
+/// How much energy comes back at the start of each of a character's turns
+const ENERGY_RECOVERY: u16 = 1;
+
+/// Who an ability is aimed at
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Targeting {
+    /// Whoever the user would otherwise have attacked
+    Enemy,
+    /// Everyone on the team of whoever the user would otherwise have attacked
+    EnemyTeam,
+    /// The most hurt character on the user's team
+    Ally,
+    User
+}
+
+/// Something an ability does to each of its targets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    Damage(u16),
+    Heal(u16),
+    Apply(Status),
+    /// Brings a summon onto the target's team for a number of turns
+    Summon(SummonKind, u8),
+    /// Blesses or curses the target with a modifier for the rest of the game
+    Grant(ModifierType),
+    /// Takes modifiers of the type away from the target for the rest of the game
+    Strip(ModifierType),
+    /// Like `Grant`, but characters keep the modifier once the game is settled
+    GrantForGood(ModifierType),
+    /// Like `Strip`, but characters don't get the modifiers back once the game is settled
+    StripForGood(ModifierType)
+}
+impl Effect {
+    pub fn event(&self, target: ActiveCharacterID, ids: &mut IdSource) -> Event {
+        match self {
+            Effect::Damage(amount) => Event::ProgressProgressBar { location: GameProgressBarLocation::Character(target, ProgressBarName::HP), amount: *amount },
+            Effect::Heal(amount) => Event::RegressProgressBar { location: GameProgressBarLocation::Character(target, ProgressBarName::HP), amount: *amount },
+            Effect::Apply(status) => Event::ApplyStatus { character: target, status: *status },
+            Effect::Summon(kind, turns) => Event::Summon { character: ids.character(), summoner: target, kind: *kind, turns: Some(*turns) },
+            Effect::Grant(modifier) => Event::AddModifier { character: target, modifier: *modifier, permanent: false },
+            Effect::Strip(modifier) => Event::RemoveModifier { character: target, modifier: *modifier, permanent: false },
+            Effect::GrantForGood(modifier) => Event::AddModifier { character: target, modifier: *modifier, permanent: true },
+            Effect::StripForGood(modifier) => Event::RemoveModifier { character: target, modifier: *modifier, permanent: true }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ability {
+    pub name: String,
+    pub targeting: Targeting,
+    pub effects: Vec<Effect>,
+    /// How many of the user's turns pass before it can be used again. 0 for no cooldown.
+    pub cooldown: u8,
+    /// Filled on one of the user's progress bars when used. The ability can't be used if that would overfill it.
+    pub cost: Option<(ProgressBarName, u16)>
+}
+impl Ability {
+    pub fn new(name: String, targeting: Targeting, effects: Vec<Effect>, cooldown: u8, cost: Option<(ProgressBarName, u16)>) -> Self {
+        Self { name, targeting, effects, cooldown, cost }
+    }
+    /// The monsters the ability summons minions of
+    pub fn minions(&self) -> impl Iterator<Item = EntityID<Monster>> + '_ {
+        self.effects.iter().filter_map(|effect| match effect {
+            Effect::Summon(SummonKind::Minion(monster), _) => Some(*monster),
+            _ => None
+        })
+    }
+    pub fn heals(&self) -> bool {
+        self.effects.iter().any(|effect| matches!(effect, Effect::Heal(_)))
+    }
+    /// Off cooldown, and affordable
+    pub fn ready(&self, user: &ActiveCharacter) -> bool {
+        let affordable = self.cost.is_none_or(|(bar, cost)| {
+            user.progress_bars.get(&bar).is_some_and(|bar| bar.progress + cost <= bar.max)
+        });
+        affordable && !user.cooldowns.contains_key(&self.name)
+    }
+}
+
+impl Game {
+    /// Ticks down the character's cooldowns, and gets back some of their energy
+    pub(crate) fn recover(&mut self, character: ActiveCharacterID) -> Vec<Event> {
+        let active_character = self.active_characters.get_mut(character);
+        active_character.cooldowns.retain(|_, turns| {
+            *turns -= 1;
+            *turns > 0
+        });
+        active_character.progress_bars.get(&ProgressBarName::Energy)
+            .filter(|energy| energy.progress > 0)
+            .map(|_| Event::RegressProgressBar { location: GameProgressBarLocation::Character(character, ProgressBarName::Energy), amount: ENERGY_RECOVERY })
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Sim<'_> {
+    /// The first ready ability that has someone worth using it on. Healing is saved for allies who are hurt.
+    pub(crate) fn choose_ability(&self, team: EntityID<Team>, attacker: ActiveCharacterID, defender: ActiveCharacterID) -> Option<(Ability, ActiveCharacterID)> {
+        let user = self.world.active_characters.get(attacker);
+        let hurt = |character: &ActiveCharacter| character.progress_bars.get(&ProgressBarName::HP).is_some_and(|hp| hp.progress > 0);
+
+        user.profile.abilities.iter()
+            .filter(|ability| ability.ready(user))
+            .find_map(|ability| {
+                let worth_it = |character: &&ActiveCharacter| !ability.heals() || hurt(character);
+                let target = match ability.targeting {
+                    Targeting::Enemy | Targeting::EnemyTeam => Some(defender),
+                    Targeting::Ally => self.world.rosters[&team].characters()
+                        .map(|(_, character)| self.world.active_characters.get(character))
+                        .filter(worth_it)
+                        .max_by_key(|character| character.progress_bars.get(&ProgressBarName::HP).map(|hp| hp.progress))
+                        .map(|character| character.id),
+                    Targeting::User => Some(user).filter(worth_it).map(|user| user.id)
+                }?;
+                Some((ability.clone(), target))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entities::{Combatant, Profile, Stats}, events::{CompletedEvent, ExecutedEvent}, progress_bars::Colour, testing::database};
+
+    #[test]
+    fn an_ability_is_ready_again_once_its_cooldown_has_passed() {
+        const COOLDOWN: u8 = 3;
+        let database = database("cooldown");
+        let ability = Ability::new("Rest".to_string(), Targeting::User, Vec::new(), COOLDOWN, None);
+        let profile = Profile { name: "Resting".to_string(), colour: Colour::Red, stats: Stats::example(), modifiers: Vec::new(), abilities: vec![ability.clone()], equipment: Vec::new() };
+        let user = ActiveCharacter::new(Combatant::Character(EntityID::roll()), profile);
+        let id = user.id;
+        let mut world = Game::default();
+        world.active_characters.add_active_character(user);
+
+        let used = Event::UseAbility { user: id, ability: ability.clone(), target: id };
+        CompletedEvent { event: ExecutedEvent(used), pre_responses: Vec::new(), outcomes: Vec::new(), post_responses: Vec::new() }.replay(&mut world, &database);
+        assert!(!ability.ready(world.active_characters.get(id)));
+
+        // Each of the user's turns starts with a tick, and it sits out the next `COOLDOWN` of them
+        for turn in 1..=COOLDOWN {
+            world.recover(id);
+            assert!(!ability.ready(world.active_characters.get(id)), "Ready again {turn} turns after being used");
+        }
+        world.recover(id);
+        assert!(ability.ready(world.active_characters.get(id)));
+    }
+}
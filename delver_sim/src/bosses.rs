@@ -0,0 +1,87 @@
+use chronobase::EntityID;
+use serde::{Deserialize, Serialize};
+
+use crate::{abilities::Ability, entities::Monster, game::ActiveCharacterID, modifiers::{Modifier, ModifierType}};
+
+/// A stage of a boss fight, starting once the boss has taken `threshold` damage
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Phase {
+    pub name: String,
+    pub threshold: u16,
+    /// Monsters that join the boss's side when the phase starts
+    pub adds: Vec<EntityID<Monster>>,
+    /// Modifiers the boss gains for the rest of the fight
+    pub modifiers: Vec<ModifierType>,
+    /// Abilities the boss can use for the rest of the fight
+    #[serde(default)]
+    pub abilities: Vec<Ability>
+}
+
+/// A monster whose health is kept on the game rather than on itself, split into phases
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Boss {
+    pub monster: EntityID<Monster>,
+    pub hp: u16,
+    /// In order of threshold
+    pub phases: Vec<Phase>
+}
+
+/// The state of the boss fight in progress
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BossFight {
+    pub boss: ActiveCharacterID,
+    pub hp: u16,
+    pub phases: Vec<Phase>,
+    /// The index of the phase the boss is in. None until the first threshold is crossed.
+    pub phase: Option<usize>,
+    /// Modifiers gained from phases so far
+    pub modifiers: Vec<Modifier>
+}
+impl BossFight {
+    pub fn new(boss: ActiveCharacterID, template: &Boss) -> Self {
+        Self {
+            boss,
+            hp: template.hp,
+            phases: template.phases.clone(),
+            phase: None,
+            modifiers: Vec::new()
+        }
+    }
+    /// The phases whose thresholds lie between the two amounts of damage taken
+    pub fn phases_crossed(&self, before: u16, after: u16) -> impl Iterator<Item = usize> + '_ {
+        self.phases.iter().enumerate()
+            .filter(move |(_, phase)| before < phase.threshold && phase.threshold <= after)
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fight(thresholds: &[u16]) -> BossFight {
+        let phases = thresholds.iter().map(|threshold| Phase {
+            name: format!("At {threshold}"),
+            threshold: *threshold,
+            adds: Vec::new(),
+            modifiers: Vec::new(),
+            abilities: Vec::new()
+        }).collect();
+        BossFight::new(ActiveCharacterID::roll(), &Boss { monster: EntityID::roll(), hp: 30, phases })
+    }
+
+    #[test]
+    fn crossing_a_threshold() {
+        let fight = fight(&[10, 20]);
+        assert_eq!(fight.phases_crossed(0, 9).count(), 0);
+        assert_eq!(fight.phases_crossed(5, 10).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(fight.phases_crossed(10, 15).count(), 0);
+    }
+
+    #[test]
+    fn one_hit_can_cross_several() {
+        let fight = fight(&[10, 20]);
+        assert_eq!(fight.phases_crossed(0, 25).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(fight.phases_crossed(10, 20).collect::<Vec<_>>(), vec![1]);
+    }
+}
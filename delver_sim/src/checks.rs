@@ -0,0 +1,35 @@
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckOutcome {
+    CriticalFailure,
+    Failure,
+    Success,
+    CriticalSuccess
+}
+impl CheckOutcome {
+    pub fn passed(&self) -> bool {
+        matches!(self, Self::Success | Self::CriticalSuccess)
+    }
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Self::CriticalFailure => "critically fails",
+            Self::Failure => "fails",
+            Self::Success => "passes",
+            Self::CriticalSuccess => "aces"
+        }
+    }
+}
+
+/// Rolls 2d10, adds the stat and takes away the difficulty. See prob.txt for the odds.
+pub fn stat_check(rng: &mut ChaCha8Rng, stat: i8, difficulty: i8) -> CheckOutcome {
+    let roll = rng.gen_range(1..=10) + rng.gen_range(1..=10) + stat as i16 - difficulty as i16;
+    match roll {
+        ..=4 => CheckOutcome::CriticalFailure,
+        5..=10 => CheckOutcome::Failure,
+        11..=17 => CheckOutcome::Success,
+        18.. => CheckOutcome::CriticalSuccess
+    }
+}
@@ -1,20 +1,21 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{collections::{BTreeMap, HashMap}, error::Error, fmt::Debug, sync::RwLock};
 
 use chronobase::{EntityID, SavableEntity, Typebase};
 use serde::{Deserialize, Serialize};
 
-use crate::{entities::{Character, Team}, game::{ActiveCharacter, ActiveCharacterID, Game}};
+use crate::{delve::Delve, dungeon::Dungeon, entities::{Character, Combatant, Monster, Profile, Team}, game::{ActiveCharacter, ActiveCharacterID, Game}, items::Item, names::{NameIndex, Named}, records::GameListing, turn_log::TurnLog};
 
 impl<> Debug for DatabaseManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         "Character Database".fmt(f)
     }
 }
-pub trait CharacterDatabase: Typebase<Character> + Typebase<Team> + Typebase<Game> + Send + Sync {}
-impl<T: Typebase<Character> + Typebase<Team> + Typebase<Game> + Send + Sync> CharacterDatabase for T {}
+pub trait CharacterDatabase: Typebase<Character> + Typebase<Team> + Typebase<Game> + Typebase<Delve> + Typebase<Dungeon> + Typebase<Monster> + Typebase<Item> + Typebase<TurnLog> + Typebase<GameListing> + Typebase<NameIndex> + Send + Sync {}
+impl<T: Typebase<Character> + Typebase<Team> + Typebase<Game> + Typebase<Delve> + Typebase<Dungeon> + Typebase<Monster> + Typebase<Item> + Typebase<TurnLog> + Typebase<GameListing> + Typebase<NameIndex> + Send + Sync> CharacterDatabase for T {}
 
-#[derive(Default, Clone, Serialize, Deserialize)]
-pub struct ActiveCharacterManager(pub HashMap<ActiveCharacterID, ActiveCharacter>);
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+/// Kept in id order, so anything that goes through every active character does it the same way each time the game is played
+pub struct ActiveCharacterManager(pub BTreeMap<ActiveCharacterID, ActiveCharacter>);
 impl ActiveCharacterManager {
     pub fn add_active_character(&mut self, character: ActiveCharacter) {
         self.0.insert(character.id, character);
@@ -27,17 +28,88 @@ impl ActiveCharacterManager {
     }
 }
 
-pub struct DatabaseManager(pub Box<dyn CharacterDatabase>);
+/// Characters and teams that have already been loaded, so looking them up again doesn't go back to the database.
+/// Saves through the manager keep it up to date. Changes made to the database any other way need the cache invalidated.
+#[derive(Default)]
+pub struct EntityCache {
+    characters: HashMap<EntityID<Character>, Character>,
+    teams: HashMap<EntityID<Team>, Team>
+}
+
+/// Why something couldn't be saved
+#[derive(Debug)]
+pub enum SaveError {
+    /// The database turned it down
+    Database,
+    /// Team names are being kept unique, and another team already has this one
+    NameTaken(String)
+}
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Database => write!(f, "The database couldn't save it"),
+            SaveError::NameTaken(name) => write!(f, "There's already a team called {name}")
+        }
+    }
+}
+impl Error for SaveError {}
+
+pub struct DatabaseManager {
+    pub database: Box<dyn CharacterDatabase>,
+    cache: Option<RwLock<EntityCache>>,
+    unique_team_names: bool
+}
 impl DatabaseManager {
+    /// Every load goes to the database
     pub fn new(database: Box<dyn CharacterDatabase>) -> Self {
-        Self(database)
+        Self { database, cache: None, unique_team_names: false }
+    }
+    /// Keeps characters and teams around once they're loaded. Only for when nothing else is writing to the database,
+    /// or the cache has to be invalidated whenever something does.
+    pub fn cached(database: Box<dyn CharacterDatabase>) -> Self {
+        Self { database, cache: Some(RwLock::default()), unique_team_names: false }
+    }
+    /// Saving a team under a name another team already has fails, rather than leaving two teams with the same name
+    pub fn with_unique_team_names(mut self) -> Self {
+        self.unique_team_names = true;
+        self
+    }
+    pub fn unique_team_names(&self) -> bool {
+        self.unique_team_names
+    }
+    pub fn invalidate_character(&self, id: EntityID<Character>) {
+        if let Some(cache) = &self.cache {
+            cache.write().unwrap().characters.remove(&id);
+        }
+    }
+    pub fn invalidate_team(&self, id: EntityID<Team>) {
+        if let Some(cache) = &self.cache {
+            cache.write().unwrap().teams.remove(&id);
+        }
+    }
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            *cache.write().unwrap() = EntityCache::default();
+        }
     }
     pub fn save<T>(&self, value: T) where Self: Save<T> {
-        _Save::save(self, value).unwrap();
+        self.try_save(value).unwrap();
+    }
+    pub fn try_save<T>(&self, value: T) -> Result<(), SaveError> where Self: Save<T> {
+        _Save::save(self, value)
     }
     pub fn load<ID, O>(&self, id: ID) -> O where Self: Load<ID, O> {
         _Load::load(self, id).unwrap()
     }
+    pub fn try_load<ID, O>(&self, id: ID) -> Option<O> where Self: Load<ID, O> {
+        _Load::load(self, id)
+    }
+    /// Every saved version of the entity, oldest first. Skips the cache.
+    pub fn history<T: SavableEntity>(&self, id: EntityID<T>) -> Vec<T> where dyn CharacterDatabase: Typebase<T> {
+        let mut versions = self.database.load(id, None, i64::MAX as usize).unwrap_or_default();
+        versions.reverse();
+        versions
+    }
 }
 
 #[allow(private_bounds)]
@@ -57,7 +129,7 @@ pub trait GetMut<ID, O>: _GetMut<ID, Output = O> {}
 impl<T, ID, O> GetMut<ID, O> for T where T: _GetMut<ID, Output = O> {}
 
 trait _Save<T> {
-    fn save(&self, value: T) -> Option<()>;
+    fn save(&self, value: T) -> Result<(), SaveError>;
 }
 trait _Load<ID> {
     type Output;
@@ -73,15 +145,89 @@ trait _GetMut<ID> {
     fn get_mut(&mut self, id: ID) -> Option<&mut Self::Output>;
 }
 
-impl<T: SavableEntity + GetIDHelpher> _Save<T> for DatabaseManager where dyn CharacterDatabase: Typebase<T>{
-    fn save(&self, value: T) -> Option<()> {   
-        self.0.save(value.get_id(), &value).ok()
+impl<T: SavableEntity + GetIDHelpher + Cached + Named> _Save<T> for DatabaseManager where dyn CharacterDatabase: Typebase<T>{
+    fn save(&self, value: T) -> Result<(), SaveError> {   
+        value.check_name(self)?;
+        self.database.save(value.get_id(), &value).map_err(|_| SaveError::Database)?;
+        if let Some(cache) = &self.cache {
+            value.cache(&mut cache.write().unwrap());
+        }
+        value.index_name(self)
     }
 }
-impl<T: SavableEntity> _Load<EntityID<T>> for DatabaseManager where dyn CharacterDatabase: Typebase<T> {
+impl<T: SavableEntity + Cached> _Load<EntityID<T>> for DatabaseManager where dyn CharacterDatabase: Typebase<T> {
     type Output = T;
     fn load(&self, id: EntityID<T>) -> Option<T> {
-        self.0.load_latest(id, None).ok()
+        if let Some(value) = self.cache.as_ref().and_then(|cache| T::cached(&cache.read().unwrap(), id)) {
+            return Some(value)
+        }
+        let value: T = self.database.load_latest(id, None).ok()?;
+        if let Some(cache) = &self.cache {
+            value.cache(&mut cache.write().unwrap());
+        }
+        Some(value)
+    }
+}
+
+/// Entities the cache holds. Anything else always goes to the database.
+trait Cached: Sized {
+    fn cached(_cache: &EntityCache, _id: EntityID<Self>) -> Option<Self> {
+        None
+    }
+    fn cache(&self, _cache: &mut EntityCache) {}
+}
+impl Cached for Character {
+    fn cached(cache: &EntityCache, id: EntityID<Self>) -> Option<Self> {
+        cache.characters.get(&id).cloned()
+    }
+    fn cache(&self, cache: &mut EntityCache) {
+        cache.characters.insert(self.id, self.clone());
+    }
+}
+impl Cached for Team {
+    fn cached(cache: &EntityCache, id: EntityID<Self>) -> Option<Self> {
+        cache.teams.get(&id).cloned()
+    }
+    fn cache(&self, cache: &mut EntityCache) {
+        cache.teams.insert(self.id, self.clone());
+    }
+}
+impl Cached for Game {}
+impl Cached for Delve {}
+impl Cached for Dungeon {}
+impl Cached for Monster {}
+impl Cached for Item {}
+impl Cached for TurnLog {}
+impl Cached for GameListing {}
+// Anyone saving a team or character can change it, so it's always read fresh
+impl Cached for NameIndex {}
+
+impl Named for Game {}
+impl Named for Delve {}
+impl Named for Dungeon {}
+impl Named for Monster {}
+impl Named for Item {}
+impl Named for TurnLog {}
+impl Named for GameListing {}
+impl Named for NameIndex {}
+
+impl _Load<Combatant> for DatabaseManager {
+    type Output = Profile;
+    fn load(&self, id: Combatant) -> Option<Profile> {
+        match id {
+            Combatant::Character(id) => {
+                let character: Character = _Load::load(self, id)?;
+                let team: Team = _Load::load(self, character.team)?;
+                let equipment = character.equipment.items().collect();
+                let stats = character.injured_stats();
+                Some(Profile { name: character.name, colour: team.colour, stats, modifiers: character.modifiers, abilities: character.abilities, equipment })
+            }
+            Combatant::Monster(id) => {
+                let monster: Monster = _Load::load(self, id)?;
+                Some(Profile { name: monster.name, colour: monster.colour, stats: monster.stats, modifiers: monster.modifiers, abilities: monster.abilities, equipment: Vec::new() })
+            }
+            Combatant::Summon(_) => None
+        }
     }
 }
 
@@ -131,4 +277,39 @@ impl GetIDHelpher for Game {
     fn get_id(&self) -> EntityID<Self> {
         self.game_id
     }
-}
\ No newline at end of file
+}
+impl GetIDHelpher for Delve {
+    fn get_id(&self) -> EntityID<Self> {
+        self.id
+    }
+}
+impl GetIDHelpher for Dungeon {
+    fn get_id(&self) -> EntityID<Self> {
+        self.id
+    }
+}
+impl GetIDHelpher for Monster {
+    fn get_id(&self) -> EntityID<Self> {
+        self.id
+    }
+}
+impl GetIDHelpher for Item {
+    fn get_id(&self) -> EntityID<Self> {
+        self.id
+    }
+}
+impl GetIDHelpher for TurnLog {
+    fn get_id(&self) -> EntityID<Self> {
+        self.id
+    }
+}
+impl GetIDHelpher for GameListing {
+    fn get_id(&self) -> EntityID<Self> {
+        self.id
+    }
+}
+impl GetIDHelpher for NameIndex {
+    fn get_id(&self) -> EntityID<Self> {
+        NameIndex::id()
+    }
+}
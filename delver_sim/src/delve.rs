@@ -0,0 +1,296 @@
+use chronobase::{EntityID, SavableEntity};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{bosses::Boss, database::{DatabaseManager, SaveError}, dungeon::Dungeon, entities::{Monster, Team}, events::Event, game::{ActiveCharacter, Game, GameOutcome, IdSource, Sim}, hazards::Hazard, loot::LootTable, progress_bars::{depth_bar, supplies_bar, ProgressBar, ProgressBarName}, records::{GameDay, GameMode, GameRecord}};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Encounter {
+    Fight {
+        defenders: EntityID<Team>
+    },
+    Monsters {
+        monsters: Vec<EntityID<Monster>>
+    },
+    Boss(Boss),
+    Trap {
+        hazard: Hazard,
+        difficulty: i8
+    },
+    Treasure {
+        loot: LootTable,
+        rolls: u8
+    },
+    Rest
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Floor {
+    pub name: String,
+    pub encounters: Vec<Encounter>
+}
+impl Floor {
+    pub fn new(name: String, encounters: Vec<Encounter>) -> Self {
+        Self { name, encounters }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DelveState {
+    InProgress,
+    Retreated,
+    Wiped,
+    /// The delvers ran from a fight, ending the delve
+    Fled,
+    /// The delvers made it through every floor
+    Completed
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Delve {
+    pub id: EntityID<Delve>,
+    pub team: EntityID<Team>,
+    /// The dungeon the floors were generated from, if any
+    pub dungeon: Option<EntityID<Dungeon>>,
+    pub floors: Vec<Floor>,
+    pub floor: usize,
+    pub encounter: usize,
+    pub depth: ProgressBar,
+    /// Spent by making camp between encounters
+    pub supplies: ProgressBar,
+    /// The delvers still standing, carried from one encounter to the next with their progress bars intact.
+    /// Empty until the first encounter begins.
+    pub party: Vec<ActiveCharacter>,
+    /// The party retreats once fewer than this many delvers are standing
+    pub retreat_below: usize,
+    pub games: Vec<EntityID<Game>>,
+    pub state: DelveState
+}
+impl Delve {
+    /// Starts at the first encounter. A delve with no encounters at all is already complete.
+    pub fn new(team: EntityID<Team>, floors: Vec<Floor>) -> Self {
+        let mut delve = Self {
+            id: EntityID::roll(),
+            team,
+            dungeon: None,
+            depth: depth_bar(floors.len() as u16),
+            supplies: supplies_bar(3),
+            floors,
+            floor: 0,
+            encounter: 0,
+            party: Vec::new(),
+            retreat_below: 2,
+            games: Vec::new(),
+            state: DelveState::InProgress
+        };
+        delve.skip_cleared_floors();
+        delve
+    }
+    pub fn from_dungeon(team: EntityID<Team>, dungeon: &Dungeon) -> Self {
+        Self {
+            dungeon: Some(dungeon.id),
+            ..Self::new(team, dungeon.floors.clone())
+        }
+    }
+    pub fn is_over(&self) -> bool {
+        self.state != DelveState::InProgress
+    }
+    pub fn current_floor(&self) -> Option<&Floor> {
+        self.floors.get(self.floor)
+    }
+    pub fn current_encounter(&self) -> Option<&Encounter> {
+        self.current_floor()?.encounters.get(self.encounter)
+    }
+    pub fn retreat(&mut self) {
+        self.state = DelveState::Retreated;
+    }
+    pub fn should_retreat(&self) -> bool {
+        self.party.len() < self.retreat_below
+    }
+    /// Whether anyone in the party is one hit from going down, and there are supplies left to camp with
+    pub fn wants_to_camp(&self) -> bool {
+        !self.supplies.complete() && self.party.iter().any(|character| {
+            character.progress_bars.get(&ProgressBarName::HP).is_some_and(|hp| hp.progress + 2 >= hp.max)
+        })
+    }
+
+    /// Moves on to the next encounter, descending a floor once this one is cleared
+    fn advance(&mut self) {
+        self.encounter += 1;
+        self.skip_cleared_floors();
+    }
+    /// Descends past floors with no encounters left, completing the delve once there are none below
+    fn skip_cleared_floors(&mut self) {
+        while self.current_floor().is_some_and(|floor| self.encounter >= floor.encounters.len()) {
+            self.floor += 1;
+            self.encounter = 0;
+            self.depth.increment(1);
+        }
+        if self.current_floor().is_none() {
+            self.state = DelveState::Completed;
+        }
+    }
+}
+
+impl<'a> Sim<'a> {
+    /// Every encounter of the delve is played on the given day of the league's calendar, if any
+    pub fn new_delve(database: DatabaseManager, rng: &'a mut ChaCha8Rng, delve: Delve, day: Option<GameDay>) -> Self {
+        database.save(delve.clone());
+        let mut world = Game::default();
+        world.record.day = day;
+        Sim {
+            database,
+            rng,
+            world,
+            delve: Some(delve),
+            log_events: true,
+            save_turns: true
+        }
+    }
+
+    /// Runs encounters until the delvers retreat, wipe or reach the bottom
+    pub fn run_delve(&mut self) -> Result<(), SaveError> {
+        while self.delve.as_ref().is_some_and(|delve| !delve.is_over()) {
+            if self.delve.as_ref().is_some_and(Delve::wants_to_camp) {
+                self.camp()?;
+            }
+            self.start_encounter();
+            while !self.finished() {
+                self.turn();
+            }
+            self.finish_encounter()?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the world with a fresh game holding just the delve's party
+    fn enter_party(&mut self) -> EntityID<Team> {
+        let delve = self.delve.as_ref().expect("Encounters need a delve");
+        let (team, party) = (delve.team, delve.party.clone());
+        let day = self.world.record.day;
+        self.world = Game::default();
+        self.world.record = GameRecord::starting(GameMode::Delve(delve.id), day);
+        self.world.ids = IdSource::seeded(self.rng);
+
+        if party.is_empty() {
+            self.add_team(team);
+        } else {
+            self.add_empty_team(team);
+            self.pack(team);
+            for active_character in party {
+                self.insert_active_character(active_character, team);
+            }
+        }
+        team
+    }
+
+    /// During a fight, the delvers run once fewer than `retreat_below` of them are standing
+    pub(crate) fn wants_to_flee(&self, team: EntityID<Team>) -> bool {
+        self.delve.as_ref().is_some_and(|delve| {
+            delve.team == team && self.world.rosters[&team].characters().count() < delve.retreat_below
+        })
+    }
+
+    /// Settles the game, then takes whoever is still on the delve team's roster, or escaped from it, back out of the world
+    fn collect_party(&mut self) -> Result<(), SaveError> {
+        self.settle()?;
+        let delve = self.delve.as_mut().expect("Encounters need a delve");
+        delve.games.push(self.world.game_id);
+        let standing = self.world.rosters.get(&delve.team).into_iter().flat_map(|roster| roster.characters().map(|(_, id)| id));
+        let escaped = self.world.escaped.get(&delve.team).into_iter().flatten().copied();
+        // Summons don't outlast the game they were summoned in
+        delve.party = standing.chain(escaped)
+            .map(|id| self.world.active_characters.get(id).clone())
+            .filter(|active_character| !active_character.is_summon())
+            .collect();
+        Ok(())
+    }
+
+    /// Rests the whole party. A full rest is only possible at a rest site.
+    fn rest(&mut self, team: EntityID<Team>, full: bool) {
+        let events = self.world.rosters[&team].characters()
+            .map(|(_, character)| Event::Rest { character, full })
+            .collect();
+        self.complete_events(events);
+    }
+
+    /// Spends some supplies to make camp between encounters. Does nothing if the supplies have run out.
+    pub fn camp(&mut self) -> Result<(), SaveError> {
+        let delve = self.delve.as_mut().expect("Camping needs a delve");
+        if delve.supplies.complete() {
+            return Ok(())
+        }
+        delve.supplies.increment(1);
+
+        let team = self.enter_party();
+        self.complete_events(vec![Event::Say("The delvers make camp".to_string())]);
+        self.rest(team, false);
+        self.collect_party()?;
+        let delve = self.delve.as_ref().expect("Camping needs a delve");
+        self.database.try_save(delve.clone())
+    }
+
+    /// Replaces the world with a fresh game for the delve's current encounter
+    pub fn start_encounter(&mut self) {
+        let delve = self.delve.as_ref().expect("Encounters need a delve");
+        let encounter = delve.current_encounter().expect("The delve is not over").clone();
+        let team = self.enter_party();
+
+        match encounter {
+            Encounter::Fight { defenders } => {
+                self.add_team(defenders);
+            }
+            Encounter::Monsters { monsters } => {
+                self.add_monsters(&monsters);
+            }
+            Encounter::Boss(boss) => {
+                self.add_boss(&boss);
+            }
+            Encounter::Trap { hazard, difficulty } => {
+                let roster = &self.world.rosters[&team];
+                let targets: Vec<_> = if hazard.hits_everyone() {
+                    roster.characters().map(|(_, character)| character).collect()
+                } else {
+                    roster.random_filled(self.rng).into_iter().collect()
+                };
+                let events = targets.into_iter().map(|target| Event::Hazard { hazard, target, difficulty }).collect();
+                self.complete_events(events);
+            }
+            Encounter::Treasure { loot, rolls } => {
+                let found: Vec<_> = (0..rolls).filter_map(|_| loot.roll(self.rng).cloned()).collect();
+                if found.is_empty() {
+                    self.complete_events(vec![Event::Say("The delvers find an empty treasure room".to_string())]);
+                } else {
+                    let events = found.into_iter().map(|item| Event::DropLoot { team, item }).collect();
+                    self.complete_events(events);
+                }
+            }
+            Encounter::Rest => {
+                self.complete_events(vec![Event::Say("The delvers find a rest site".to_string())]);
+                self.rest(team, true);
+            }
+        }
+    }
+
+    /// Carries the survivors of the current encounter forward, and saves the delve's progress
+    pub fn finish_encounter(&mut self) -> Result<(), SaveError> {
+        self.collect_party()?;
+        let delve = self.delve.as_mut().expect("Encounters need a delve");
+
+        if delve.party.is_empty() {
+            delve.state = DelveState::Wiped;
+        } else if self.world.outcome() == Some(GameOutcome::Escape(delve.team)) {
+            delve.state = DelveState::Fled;
+        } else {
+            delve.advance();
+            if !delve.is_over() && delve.should_retreat() {
+                delve.retreat();
+            }
+        }
+        self.database.try_save(delve.clone())
+    }
+}
+
+impl SavableEntity for Delve {
+    const TABLE_NAME: &'static str = "delves";
+}
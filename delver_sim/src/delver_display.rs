@@ -3,7 +3,8 @@ use std::fmt::Display;
 use colored::Colorize;
 use itertools::Itertools;
 
-use crate::{database::DatabaseManager, entities::{Character, Stats}, events::{CompletedEvent, Event, ExecutedEvent}, game::{ActiveCharacter, ActiveCharacterID, Game}, progress_bars::{GameProgressBarLocation, ProgressBar}};
+use chronobase::EntityID;
+use crate::{abilities::Targeting, database::DatabaseManager, delve::{Delve, DelveState}, entities::{Character, Monster, Profile, Stats, Team, XP_PER_LEVEL}, events::{CompletedEvent, Event, ExecutedEvent}, game::{ActiveCharacter, ActiveCharacterID, Game, GameOutcome}, items::Item, progress_bars::{Colour, GameProgressBarLocation, ProgressBar, ProgressBarName}, records::{GameDay, GameMode, GameRecord}};
 
 pub trait ToDisplayConstruct {
     fn shortform(&self, game: &Game, database: &DatabaseManager) -> String;
@@ -39,11 +40,70 @@ impl ToDisplayConstruct for Character {
         DisplayConstruct::Multi(
             vec![
                 DisplayConstruct::Single(name),
+                DisplayConstruct::Single(format!("Level {} ({}/{XP_PER_LEVEL} XP)", self.level, self.xp)),
+                self.stats.longform(game, database),
+                DisplayConstruct::List(self.equipment.items().map(|item| database.load(item).shortform(game, database)).collect()),
+                DisplayConstruct::List(self.injuries.iter().map(|stat| format!("Injured {stat}")).collect()),
+                DisplayConstruct::Single(format!("{} games, {} victories, {} escapes, downed {} times", self.career.games, self.career.victories, self.career.escapes, self.career.downs))
+            ]
+        )
+    }
+}
+impl ToDisplayConstruct for Item {
+    fn shortform(&self, _game: &Game, _database: &DatabaseManager) -> String {
+        self.name().to_string()
+    }
+    fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
+        let mut details = vec![DisplayConstruct::Single(self.shortform(game, database))];
+        if let Some(slot) = self.template.slot {
+            details.push(DisplayConstruct::List(
+                std::iter::once(format!("{slot:?}"))
+                    .chain(self.template.bonuses.iter().map(|(stat, bonus)| format!("{stat}: {bonus:+}")))
+                    .chain(self.template.modifiers.iter().map(|modifier| format!("{modifier:?}")))
+                    .collect()
+            ));
+        }
+        DisplayConstruct::Multi(details)
+    }
+}
+impl ToDisplayConstruct for Monster {
+    fn shortform(&self, _game: &Game, _database: &DatabaseManager) -> String {
+        self.name.color(self.colour).to_string()
+    }
+    fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
+        DisplayConstruct::Multi(
+            vec![
+                DisplayConstruct::Single(self.shortform(game, database)),
+                self.stats.longform(game, database)
+            ]
+        )
+    }
+}
+impl ToDisplayConstruct for Profile {
+    fn shortform(&self, _game: &Game, _database: &DatabaseManager) -> String {
+        self.name.color(self.colour).to_string()
+    }
+    fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
+        DisplayConstruct::Multi(
+            vec![
+                DisplayConstruct::Single(self.shortform(game, database)),
                 self.stats.longform(game, database)
             ]
         )
     }
 }
+/// Monster rosters have team ids that were never saved, so they're named generically
+impl ToDisplayConstruct for EntityID<Team> {
+    fn shortform(&self, _game: &Game, database: &DatabaseManager) -> String {
+        match database.try_load(*self) {
+            Some(Team { name, colour, .. }) => name.color(colour).to_string(),
+            None => "The monsters".color(Colour::Gray).to_string()
+        }
+    }
+    fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
+        DisplayConstruct::Single(self.shortform(game, database))
+    }
+}
 impl ToDisplayConstruct for ActiveCharacterID {
     fn shortform(&self, game: &Game, database: &DatabaseManager) -> String {
         game.active_characters.get(*self).shortform(game, database)
@@ -54,13 +114,21 @@ impl ToDisplayConstruct for ActiveCharacterID {
 }
 impl ToDisplayConstruct for ActiveCharacter {
     fn shortform(&self, game: &Game, database: &DatabaseManager) -> String {
-        database.load(self.character).shortform(game, database)
+        self.profile.shortform(game, database)
     }
     fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
         DisplayConstruct::Multi(vec![
             DisplayConstruct::Single(self.shortform(game, database)),
+            DisplayConstruct::Single(format!("{} -> {}", self.profile.stats.shortform(game, database), self.stats.shortform(game, database))),
             DisplayConstruct::List(
-                self.progress_bars.values().map(|bar| bar.longform(game, database).to_string()).collect()
+                game.boss.as_ref().filter(|fight| fight.boss == self.id)
+                    .and_then(|_| game.progress_bars.get(&ProgressBarName::BossHP))
+                    .into_iter()
+                    .chain(self.progress_bars.values())
+                    .map(|bar| bar.longform(game, database).to_string())
+                    .chain(self.statuses.iter().map(|status| status.to_string()))
+                    .chain(self.cooldowns.iter().map(|(ability, turns)| format!("{ability} ready in {turns}")))
+                    .collect()
             )
         ])
     }
@@ -72,6 +140,9 @@ impl ToDisplayConstruct for GameProgressBarLocation {
                 let character = game.active_characters.get(*character).shortform(game, database);
                 format!("{character}'s {bar_name:?}")
             }
+            GameProgressBarLocation::Game(bar_name) => {
+                format!("The {bar_name:?}")
+            }
         }
     }
     fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
@@ -106,6 +177,108 @@ impl ToDisplayConstruct for Event {
 
                 format!("{} {change_verb}s by {amount}", location.shortform(game, database))
             }
+            Self::RegressProgressBar { location, amount } => {
+                let bar = game.get_progress_bar(*location);
+                let change_verb = bar.style.reverse_verb();
+
+                format!("{} {change_verb}s by {amount}", location.shortform(game, database))
+            }
+            Self::Rest { character, full: true } => {
+                format!("{} takes a long rest", character.shortform(game, database))
+            }
+            Self::Rest { character, full: false } => {
+                format!("{} rests", character.shortform(game, database))
+            }
+            Self::SwapPositions { team, first, second } => {
+                format!("{} swap their {first:?} and {second:?} positions", team.shortform(game, database))
+            }
+            Self::MovePosition { character, position } => {
+                format!("{} moves to {position:?}", character.shortform(game, database))
+            }
+            Self::LeaveRoster { character } => {
+                format!("{} leaves the roster", character.shortform(game, database))
+            }
+            Self::Downed { character } => {
+                format!("{} is downed", character.shortform(game, database))
+            }
+            Self::Hazard { hazard, target, .. } => {
+                format!("{} {}", hazard.description(), target.shortform(game, database))
+            }
+            Self::HazardResult { hazard, target, outcome } => {
+                format!("{} {} their {} check", target.shortform(game, database), outcome.verb(), hazard.stat())
+            }
+            Self::ApplyStatus { character, status } => {
+                format!("{} is {status}", character.shortform(game, database))
+            }
+            Self::ClearStatus { character, status } => {
+                format!("{} is no longer {status}", character.shortform(game, database))
+            }
+            Self::Flee { character } => {
+                format!("{} tries to flee", character.shortform(game, database))
+            }
+            Self::FleeResult { character, outcome } => {
+                format!("{} {} their run check", character.shortform(game, database), outcome.verb())
+            }
+            Self::Escaped { character, .. } => {
+                format!("{} escapes!", character.shortform(game, database))
+            }
+            Self::Retreat { team } => {
+                format!("{} try to flee the fight", team.shortform(game, database))
+            }
+            Self::SpawnMonster { character, monster, team } => {
+                // Once spawned, the monster is shown as it was when it joined
+                let monster = match game.active_characters.0.get(character) {
+                    Some(active_character) => active_character.shortform(game, database),
+                    None => database.load(*monster).shortform(game, database)
+                };
+                format!("{monster} joins {}", team.shortform(game, database))
+            }
+            Self::EnterPhase { boss, phase } => {
+                let name = game.boss.as_ref().map(|fight| fight.phases[*phase].name.as_str()).unwrap_or_default();
+                format!("{} enters its {name} phase!", boss.shortform(game, database))
+            }
+            Self::DropLoot { team, item } => {
+                format!("{} find {}", team.shortform(game, database), item.name)
+            }
+            Self::UseAbility { user, ability, target } => {
+                match ability.targeting {
+                    Targeting::User => format!("{} uses {}", user.shortform(game, database), ability.name),
+                    Targeting::EnemyTeam => {
+                        let team = game.team_of(*target).map(|team| team.shortform(game, database)).unwrap_or_else(|| "their foes".to_string());
+                        format!("{} uses {} on {team}", user.shortform(game, database), ability.name)
+                    }
+                    Targeting::Enemy | Targeting::Ally => format!("{} uses {} on {}", user.shortform(game, database), ability.name, target.shortform(game, database))
+                }
+            }
+            Self::Summon { character, summoner, .. } => {
+                if game.active_characters.0.contains_key(character) {
+                    format!("{} summons {}", summoner.shortform(game, database), character.shortform(game, database))
+                } else {
+                    format!("{}'s summoning fails", summoner.shortform(game, database))
+                }
+            }
+            Self::AddModifier { character, modifier, permanent } => {
+                let permanence = if *permanent { " for good" } else { "" };
+                format!("{} gains {modifier:?}{permanence}", character.shortform(game, database))
+            }
+            Self::RemoveModifier { character, modifier, permanent } => {
+                let permanence = if *permanent { " for good" } else { "" };
+                format!("{} loses {modifier:?}{permanence}", character.shortform(game, database))
+            }
+            Self::Expire { character } => {
+                format!("{} fades away", character.shortform(game, database))
+            }
+            Self::StartTurn { character, .. } => {
+                format!("Turn {}: {}", game.turn, character.shortform(game, database))
+            }
+            Self::UseItem { user, item, target } => {
+                let item = database.load(*item).shortform(game, database);
+                if user == target {
+                    format!("{} uses {item}", user.shortform(game, database))
+                } else {
+                    format!("{} uses {item} on {}", user.shortform(game, database), target.shortform(game, database))
+                }
+            }
             Self::Say(string) => string.clone()
         }
     }
@@ -117,10 +290,23 @@ impl ToDisplayConstruct for Event {
             Self::CreateProgressBar { .. } => {
                 DisplayConstruct::Single(self.shortform(game, database))
             }
-            Self::ProgressProgressBar { location, .. } => {
+            Self::ProgressProgressBar { location, .. } | Self::RegressProgressBar { location, .. } => {
                 let bar = game.get_progress_bar(*location);
                 DisplayConstruct::Single(format!("{} => {bar}", self.shortform(game, database)))
             }
+            Self::SwapPositions { .. } | Self::MovePosition { .. } | Self::LeaveRoster { .. } | Self::Downed { .. } => {
+                DisplayConstruct::Single(self.shortform(game, database))
+            }
+            Self::Hazard { .. } | Self::HazardResult { .. } | Self::ApplyStatus { .. } | Self::ClearStatus { .. } | Self::AddModifier { .. } | Self::RemoveModifier { .. } => {
+                DisplayConstruct::Single(self.shortform(game, database))
+            }
+            Self::SpawnMonster { .. } | Self::Summon { .. } | Self::Expire { .. } | Self::EnterPhase { .. } | Self::Rest { .. } | Self::StartTurn { .. } => {
+                DisplayConstruct::Single(self.shortform(game, database))
+            }
+            Self::Flee { .. } | Self::FleeResult { .. } | Self::Escaped { .. } | Self::Retreat { .. } => {
+                DisplayConstruct::Single(self.shortform(game, database))
+            }
+            Self::DropLoot { .. } | Self::UseItem { .. } | Self::UseAbility { .. } => DisplayConstruct::Single(self.shortform(game, database)),
             Self::Say(_) => DisplayConstruct::Single(self.shortform(game, database))
         }
     }
@@ -134,6 +320,57 @@ impl ToDisplayConstruct for ExecutedEvent {
     }
 }
 
+impl ToDisplayConstruct for Delve {
+    fn shortform(&self, game: &Game, database: &DatabaseManager) -> String {
+        let state = match self.state {
+            DelveState::InProgress => match self.current_floor() {
+                Some(floor) => format!("on {}", floor.name),
+                None => "underway".to_string()
+            },
+            DelveState::Retreated => "retreated".to_string(),
+            DelveState::Wiped => "wiped out".to_string(),
+            DelveState::Fled => "fled".to_string(),
+            DelveState::Completed => "reached the bottom".to_string()
+        };
+        format!("{}'s delve, {state}", self.team.shortform(game, database))
+    }
+    fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
+        DisplayConstruct::Multi(vec![
+            DisplayConstruct::Single(self.shortform(game, database)),
+            self.depth.longform(game, database),
+            self.supplies.longform(game, database),
+            DisplayConstruct::Multi(self.party.iter().map(|character| character.longform(game, database)).collect())
+        ])
+    }
+}
+
+impl ToDisplayConstruct for GameRecord {
+    fn shortform(&self, game: &Game, database: &DatabaseManager) -> String {
+        let mode = match self.mode {
+            GameMode::Arena => "Arena",
+            GameMode::Delve(_) => "Delve"
+        };
+        let teams = self.teams.iter().map(|team| team.shortform(game, database)).join(" vs ");
+        match self.day {
+            Some(GameDay { season, day }) => format!("{mode}: {teams}, season {season} day {day}"),
+            None => format!("{mode}: {teams}")
+        }
+    }
+    fn longform(&self, game: &Game, database: &DatabaseManager) -> DisplayConstruct {
+        let result = match self.outcome {
+            Some(GameOutcome::Victory(team)) => format!("{} won after {} turns", team.shortform(game, database), self.turns),
+            Some(GameOutcome::Escape(team)) => format!("{} got away after {} turns", team.shortform(game, database), self.turns),
+            Some(GameOutcome::Draw) => format!("Nobody was left standing after {} turns", self.turns),
+            None if self.ended.is_some() => format!("Called off after {} turns", self.turns),
+            None => "Still being played".to_string()
+        };
+        DisplayConstruct::Multi(vec![
+            DisplayConstruct::Single(self.shortform(game, database)),
+            DisplayConstruct::Single(result)
+        ])
+    }
+}
+
 pub enum DisplayConstruct {
     Single(String),
     List(Vec<String>),
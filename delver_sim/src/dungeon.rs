@@ -0,0 +1,185 @@
+use std::ops::RangeInclusive;
+
+use chronobase::{EntityID, SavableEntity};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{bosses::Boss, delve::{Encounter, Floor}, entities::{Monster, Team}, hazards::Hazard, loot::LootTable};
+
+const FLOOR_ADJECTIVES: [&str; 8] = ["Damp", "Howling", "Sunken", "Gilded", "Forgotten", "Crooked", "Hollow", "Drowned"];
+const FLOOR_NOUNS: [&str; 8] = ["Halls", "Caverns", "Vaults", "Warrens", "Galleries", "Catacombs", "Cisterns", "Stacks"];
+
+#[derive(Clone, Copy, Debug)]
+enum RoomKind {
+    Fight,
+    Trap,
+    Treasure,
+    Rest
+}
+
+/// What to generate a dungeon from. The same settings and seed always give the same dungeon.
+#[derive(Clone, Debug)]
+pub struct DungeonSettings {
+    pub floors: usize,
+    pub rooms_per_floor: RangeInclusive<usize>,
+    /// Teams that can be fought, ordered from easiest to hardest. Deeper floors draw from further along.
+    pub defenders: Vec<EntityID<Team>>,
+    /// Monsters that can be fought, ordered from easiest to hardest. Preferred over defenders when there are any.
+    /// Deeper floors draw from further along, in bigger groups.
+    pub monsters: Vec<EntityID<Monster>>,
+    /// If there are any, one of them guards the bottom floor
+    pub bosses: Vec<Boss>,
+    /// What treasure rooms hold. Deeper rooms roll it more times.
+    pub treasure: LootTable
+}
+impl DungeonSettings {
+    pub fn validate(&self) -> Result<(), InvalidDungeonSettings> {
+        if self.rooms_per_floor.is_empty() {
+            return Err(InvalidDungeonSettings::NoRoomCounts)
+        }
+        Ok(())
+    }
+}
+impl Default for DungeonSettings {
+    fn default() -> Self {
+        Self {
+            floors: 3,
+            rooms_per_floor: 2..=4,
+            defenders: Vec::new(),
+            monsters: Vec::new(),
+            bosses: Vec::new(),
+            treasure: LootTable::default()
+        }
+    }
+}
+
+/// Why a dungeon couldn't be generated from the settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidDungeonSettings {
+    /// There's no number of rooms a floor could have
+    NoRoomCounts
+}
+impl std::fmt::Display for InvalidDungeonSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidDungeonSettings::NoRoomCounts => write!(f, "The range of rooms per floor is empty")
+        }
+    }
+}
+impl std::error::Error for InvalidDungeonSettings {}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dungeon {
+    pub id: EntityID<Dungeon>,
+    pub name: String,
+    pub seed: u64,
+    pub floors: Vec<Floor>
+}
+impl Dungeon {
+    pub fn generate(seed: u64, settings: &DungeonSettings) -> Result<Self, InvalidDungeonSettings> {
+        settings.validate()?;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let floors: Vec<_> = (0..settings.floors).map(|depth| generate_floor(&mut rng, settings, depth)).collect();
+        let name = floors.first().map(|floor| floor.name.clone()).unwrap_or_else(|| "The Empty Dungeon".to_string());
+        Ok(Self {
+            id: EntityID::roll(),
+            name,
+            seed,
+            floors
+        })
+    }
+}
+
+fn generate_floor(rng: &mut ChaCha8Rng, settings: &DungeonSettings, depth: usize) -> Floor {
+    let name = format!("The {} {}", FLOOR_ADJECTIVES.choose(rng).unwrap(), FLOOR_NOUNS.choose(rng).unwrap());
+    let rooms = rng.gen_range(settings.rooms_per_floor.clone());
+
+    let fight_weight = if settings.defenders.is_empty() && settings.monsters.is_empty() { 0 } else { 10 };
+    let kinds = [(RoomKind::Fight, fight_weight), (RoomKind::Trap, 4), (RoomKind::Treasure, 3), (RoomKind::Rest, 3)];
+
+    let mut encounters: Vec<_> = (0..rooms).map(|_| {
+        let (kind, _) = kinds.choose_weighted(rng, |(_, weight)| *weight).unwrap();
+        generate_encounter(rng, settings, depth, *kind)
+    }).collect();
+
+    // Every floor is guarded on the way down, the last one by a boss
+    let boss = if depth + 1 == settings.floors { settings.bosses.choose(rng) } else { None };
+    if let Some(boss) = boss {
+        encounters.push(Encounter::Boss(boss.clone()));
+    } else if fight_weight > 0 {
+        encounters.push(generate_encounter(rng, settings, depth, RoomKind::Fight));
+    }
+
+    Floor::new(name, encounters)
+}
+
+fn generate_encounter(rng: &mut ChaCha8Rng, settings: &DungeonSettings, depth: usize, kind: RoomKind) -> Encounter {
+    // Tops out rather than wrapping round on very deep floors
+    let difficulty = i8::try_from(depth + 1).unwrap_or(i8::MAX);
+    match kind {
+        RoomKind::Fight if !settings.monsters.is_empty() => {
+            let pool = tier(&settings.monsters, depth, settings.floors);
+            let monsters = (0..=depth.min(5)).map(|_| *pool.choose(rng).unwrap()).collect();
+            Encounter::Monsters { monsters }
+        }
+        RoomKind::Fight => {
+            let defenders = *tier(&settings.defenders, depth, settings.floors).choose(rng).unwrap();
+            Encounter::Fight { defenders }
+        }
+        RoomKind::Trap => Encounter::Trap { hazard: *Hazard::all().choose(rng).unwrap(), difficulty },
+        RoomKind::Treasure => Encounter::Treasure { loot: settings.treasure.clone(), rolls: difficulty as u8 },
+        RoomKind::Rest => Encounter::Rest
+    }
+}
+
+/// The part of an easiest-to-hardest list that's appropriate at the given depth
+fn tier<T>(list: &[T], depth: usize, floors: usize) -> &[T] {
+    let tier = (depth * list.len() / floors).min(list.len() - 1);
+    &list[tier.saturating_sub(1)..=tier]
+}
+
+impl SavableEntity for Dungeon {
+    const TABLE_NAME: &'static str = "dungeons";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> DungeonSettings {
+        DungeonSettings {
+            floors: 4,
+            defenders: (0..5).map(|_| EntityID::roll()).collect(),
+            ..DungeonSettings::default()
+        }
+    }
+    /// Dungeons get an id of their own, so only what was generated is compared
+    fn layout(dungeon: &Dungeon) -> String {
+        format!("{:?}", (&dungeon.name, &dungeon.floors))
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_dungeon() {
+        let settings = settings();
+        let first = Dungeon::generate(7, &settings).unwrap();
+        let second = Dungeon::generate(7, &settings).unwrap();
+        assert_eq!(layout(&first), layout(&second));
+        assert_eq!(first.floors.len(), settings.floors);
+    }
+
+    #[test]
+    fn every_floor_is_guarded() {
+        let dungeon = Dungeon::generate(7, &settings()).unwrap();
+        for floor in &dungeon.floors {
+            assert!(matches!(floor.encounters.last(), Some(Encounter::Fight { .. })));
+        }
+    }
+
+    #[test]
+    fn no_room_counts_is_invalid() {
+        #[allow(clippy::reversed_empty_ranges)]
+        let settings = DungeonSettings { rooms_per_floor: 3..=2, ..settings() };
+        assert_eq!(Dungeon::generate(7, &settings).err(), Some(InvalidDungeonSettings::NoRoomCounts));
+    }
+}
@@ -2,7 +2,10 @@ use std::{fmt::{Debug, Display}, ops::{Index, IndexMut}};
 use chronobase::{EntityID, SavableEntity};
 use serde::{Deserialize, Serialize};
 
-use crate::{modifiers::Modifier, progress_bars::Colour};
+use crate::{abilities::Ability, game::{ActiveCharacterID, Game}, items::{Equipment, Item}, loot::LootTable, modifiers::Modifier, progress_bars::Colour};
+
+/// Experience needed for each level
+pub const XP_PER_LEVEL: u16 = 10;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Character {
@@ -10,7 +13,27 @@ pub struct Character {
     pub name: String,
     pub team: EntityID<Team>,
     pub stats: Stats,
-    pub modifiers: Vec<Modifier>
+    pub modifiers: Vec<Modifier>,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    #[serde(default)]
+    pub equipment: Equipment,
+    /// Towards the next level
+    #[serde(default)]
+    pub xp: u16,
+    #[serde(default = "first_level")]
+    pub level: u8,
+    /// Each injury takes a point off its stat
+    #[serde(default)]
+    pub injuries: Vec<Stat>,
+    #[serde(default)]
+    pub career: Career,
+    /// The game that caused this version of the character, if any
+    #[serde(default)]
+    pub last_game: Option<EntityID<Game>>
+}
+fn first_level() -> u8 {
+    1
 }
 
 impl Character {
@@ -21,14 +44,53 @@ impl Character {
             team,
             stats,
             modifiers: Vec::new(),
+            abilities: Vec::new(),
+            equipment: Equipment::default(),
+            xp: 0,
+            level: first_level(),
+            injuries: Vec::new(),
+            career: Career::default(),
+            last_game: None
         }
     }
     pub fn modifiers(&self) -> impl Iterator<Item = &Modifier> {
         self.modifiers.iter()
     }
+    /// Puts the item in its slot, returning whatever was there before. Items without a slot aren't equipped.
+    pub fn equip(&mut self, item: &Item) -> Option<EntityID<Item>> {
+        let slot = item.template.slot?;
+        self.equipment[slot].replace(item.id)
+    }
     pub fn roll(name: String, stats:Stats, team: EntityID<Team>) -> Self {
         Self::new(name, stats, team)
     }
+    /// Their stats with injuries taken off
+    pub fn injured_stats(&self) -> Stats {
+        let mut stats = self.stats.clone();
+        for stat in &self.injuries {
+            stats[*stat] = stats[*stat].saturating_sub(1);
+        }
+        stats
+    }
+    /// Returns how many levels were gained
+    pub fn gain_xp(&mut self, xp: u16) -> u8 {
+        self.xp += xp;
+        let levels = self.xp / XP_PER_LEVEL;
+        self.xp %= XP_PER_LEVEL;
+        self.level = self.level.saturating_add(levels as u8);
+        levels as u8
+    }
+}
+
+/// Counts kept over a character's or team's whole career
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Career {
+    /// Only fights count, not traps, treasure or rest
+    pub games: u32,
+    pub victories: u32,
+    pub escapes: u32,
+    /// Times downed. For teams, the total across the roster.
+    pub downs: u32
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -84,7 +146,7 @@ impl Stats {
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Stat {
     Violence,
     Bloodthirst,
@@ -114,7 +176,14 @@ pub struct Team {
     pub id: EntityID<Team>,
     pub name: String,
     pub colour:Colour,
-    pub roster: Vec<EntityID<Character>>
+    pub roster: Vec<EntityID<Character>>,
+    #[serde(default)]
+    pub inventory: Vec<EntityID<Item>>,
+    #[serde(default)]
+    pub career: Career,
+    /// The game that caused this version of the team, if any
+    #[serde(default)]
+    pub last_game: Option<EntityID<Game>>
 }
 impl Team {
     pub fn new(name: String, colour: Colour) -> Self {
@@ -122,16 +191,82 @@ impl Team {
             id: EntityID::roll(),
             name,
             colour,
-            roster: Vec::new()
+            roster: Vec::new(),
+            inventory: Vec::new(),
+            career: Career::default(),
+            last_game: None
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Monster {
+    pub id: EntityID<Monster>,
+    pub name: String,
+    pub colour: Colour,
+    pub stats: Stats,
+    pub modifiers: Vec<Modifier>,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    pub loot_table: LootTable
+}
+impl Monster {
+    pub fn new(name: String, colour: Colour, stats: Stats) -> Self {
+        Monster {
+            id: EntityID::roll(),
+            name,
+            colour,
+            stats,
+            modifiers: Vec::new(),
+            abilities: Vec::new(),
+            loot_table: LootTable::default()
         }
     }
 }
 
+/// Whoever is behind an active character
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Combatant {
+    Character(EntityID<Character>),
+    Monster(EntityID<Monster>),
+    /// Summoned mid-game, with nothing behind it in the database. Its profile is on the game, under its active character id.
+    Summon(ActiveCharacterID)
+}
+impl From<EntityID<Character>> for Combatant {
+    fn from(value: EntityID<Character>) -> Self {
+        Self::Character(value)
+    }
+}
+impl From<EntityID<Monster>> for Combatant {
+    fn from(value: EntityID<Monster>) -> Self {
+        Self::Monster(value)
+    }
+}
 
+/// The parts of a combatant the sim cares about, whether it's a character or a monster
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub colour: Colour,
+    /// After injuries, before any equipment
+    pub stats: Stats,
+    pub modifiers: Vec<Modifier>,
+    pub abilities: Vec<Ability>,
+    pub equipment: Vec<EntityID<Item>>
+}
+impl Profile {
+    pub fn modifiers(&self) -> impl Iterator<Item = &Modifier> {
+        self.modifiers.iter()
+    }
+}
 
 impl SavableEntity for Character {
     const TABLE_NAME: &'static str = "characters";
 }
 impl SavableEntity for Team {
     const TABLE_NAME: &'static str = "teams";
+}
+impl SavableEntity for Monster {
+    const TABLE_NAME: &'static str = "monsters";
 }
\ No newline at end of file
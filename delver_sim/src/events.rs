@@ -1,29 +1,44 @@
 
+use chronobase::EntityID;
 use serde::{Deserialize, Serialize};
 
-use crate::{game::{ActiveCharacterID, Sim}, progress_bars::{GameProgressBarLocation, ProgressBar, ProgressBarName}};
+use crate::{abilities::{Ability, Targeting}, checks::{stat_check, CheckOutcome}, database::DatabaseManager, entities::{Combatant, Monster, Team}, game::{ActiveCharacter, ActiveCharacterID, Game, Position, Sim}, hazards::Hazard, items::{Consumable, Item, ItemTemplate}, modifiers::{Modifier, ModifierChange, ModifierType}, progress_bars::{energy_bar, GameProgressBarLocation, ProgressBar, ProgressBarName}, statuses::Status, summons::{Summon, SummonKind}};
+
+const CAMP_HEALING: u16 = 1;
+const FULL_REST_HEALING: u16 = 4;
 
 impl Sim<'_> {
-    fn get_pre_responses(&self, event: &mut Event) -> Vec<Event> {
-        self.world.active_characters.0.values()
-            .flat_map(|active_character| {
-                let character = self.database.load(active_character.character);
-                character.modifiers.iter().flat_map(|modifier| modifier.pre_event(active_character, event))
-                    .collect::<Vec<_>>()
-            })
-            .collect()
+    /// Asks every modifier in play for its response. Anyone who has used up their reactions this turn doesn't get to react again.
+    fn get_responses(&mut self, mut respond: impl FnMut(&Modifier, &ActiveCharacter, &Game) -> Vec<Event>) -> Vec<Event> {
+        let mut responses = Vec::new();
+        let mut reacted = Vec::new();
+        for active_character in self.world.active_characters.0.values() {
+            // Counted as they go, so a character with several reactions can't use them all on the one event
+            let mut reactions = 0;
+            for modifier in self.modifiers_of(active_character) {
+                let reaction = modifier.1.is_reaction();
+                if reaction && !self.world.can_react(active_character.id, reactions) {
+                    continue
+                }
+                let events = respond(&modifier, active_character, &self.world);
+                if reaction && !events.is_empty() {
+                    reactions += 1;
+                    reacted.push(active_character.id);
+                }
+                responses.extend(events);
+            }
+        }
+        self.world.record_reactions(reacted);
+        responses
     }
-    fn get_post_responses(&self, event: &ExecutedEvent) -> Vec<Event> {
-        self.world.active_characters.0.values()
-            .flat_map(|active_character| {
-                let character = self.database.load(active_character.character);
-                character.modifiers.iter().flat_map(|modifier| modifier.post_event(active_character, event))
-                    .collect::<Vec<_>>()
-            })
-            .collect()
+    fn get_pre_responses(&mut self, event: &mut Event) -> Vec<Event> {
+        self.get_responses(|modifier, active_character, game| modifier.pre_event(active_character, game, event))
+    }
+    fn get_post_responses(&mut self, event: &ExecutedEvent, outcomes: &[CompletedEvent]) -> Vec<Event> {
+        self.get_responses(|modifier, active_character, game| modifier.post_event(active_character, game, event, outcomes))
     }
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Event {
     Attack {
         attacker: ActiveCharacterID,
@@ -37,25 +52,375 @@ pub enum Event {
         location: GameProgressBarLocation,
         amount: u16
     },
+    RegressProgressBar {
+        location: GameProgressBarLocation,
+        amount: u16
+    },
+    SwapPositions {
+        team: EntityID<Team>,
+        first: Position,
+        second: Position
+    },
+    MovePosition {
+        character: ActiveCharacterID,
+        position: Position
+    },
+    LeaveRoster {
+        character: ActiveCharacterID
+    },
+    Downed {
+        character: ActiveCharacterID
+    },
+    Hazard {
+        hazard: Hazard,
+        target: ActiveCharacterID,
+        difficulty: i8
+    },
+    HazardResult {
+        hazard: Hazard,
+        target: ActiveCharacterID,
+        outcome: CheckOutcome
+    },
+    ApplyStatus {
+        character: ActiveCharacterID,
+        status: Status
+    },
+    ClearStatus {
+        character: ActiveCharacterID,
+        status: Status
+    },
+    Rest {
+        character: ActiveCharacterID,
+        full: bool
+    },
+    Flee {
+        character: ActiveCharacterID
+    },
+    FleeResult {
+        character: ActiveCharacterID,
+        outcome: CheckOutcome
+    },
+    Escaped {
+        character: ActiveCharacterID,
+        team: EntityID<Team>
+    },
+    /// Everyone on the team tries to flee
+    Retreat {
+        team: EntityID<Team>
+    },
+    SpawnMonster {
+        character: ActiveCharacterID,
+        monster: EntityID<Monster>,
+        team: EntityID<Team>
+    },
+    EnterPhase {
+        boss: ActiveCharacterID,
+        phase: usize
+    },
+    DropLoot {
+        team: EntityID<Team>,
+        item: ItemTemplate
+    },
+    UseItem {
+        user: ActiveCharacterID,
+        item: EntityID<Item>,
+        target: ActiveCharacterID
+    },
+    UseAbility {
+        user: ActiveCharacterID,
+        ability: Ability,
+        target: ActiveCharacterID
+    },
+    /// Brings a new active character onto the summoner's team, with nothing behind it in the database
+    Summon {
+        character: ActiveCharacterID,
+        summoner: ActiveCharacterID,
+        kind: SummonKind,
+        turns: Option<u8>
+    },
+    /// Gives the character a new modifier for the rest of the game, or for good if it's permanent
+    AddModifier {
+        character: ActiveCharacterID,
+        modifier: ModifierType,
+        permanent: bool
+    },
+    /// Takes every modifier of the type away from the character for the rest of the game, or for good if it's permanent
+    RemoveModifier {
+        character: ActiveCharacterID,
+        modifier: ModifierType,
+        permanent: bool
+    },
+    /// A summon fades away
+    Expire {
+        character: ActiveCharacterID
+    },
+    /// The character's turn begins. Comes before anything else that happens in the turn.
+    StartTurn {
+        team: EntityID<Team>,
+        character: ActiveCharacterID
+    },
     Say(String)
 }
 impl Event {
+    /// The character who caused the event, for events that have one
+    pub fn source(&self) -> Option<ActiveCharacterID> {
+        match self {
+            Event::Attack { attacker, .. } => Some(*attacker),
+            Event::UseItem { user, .. } | Event::UseAbility { user, .. } => Some(*user),
+            _ => None
+        }
+    }
     fn execute(self, sim: &mut Sim) -> (ExecutedEvent, Vec<Event>) {
+        let events = match &self {
+            // Checks are only rolled here, so replaying the game doesn't roll them again
+            Event::Hazard { hazard, target, difficulty } => {
+                let stats = sim.stats_of(sim.world.active_characters.get(*target));
+                let outcome = stat_check(sim.rng, stats[hazard.stat()], *difficulty);
+                vec![Event::HazardResult { hazard: *hazard, target: *target, outcome }]
+            }
+            Event::Flee { character } => {
+                let team = sim.world.team_of(*character);
+                let run = sim.stats_of(sim.world.active_characters.get(*character)).run;
+                // The fleeing character has to outrun the fastest of their pursuers
+                let pursuit = sim.world.standing_teams()
+                    .filter(|pursuers| Some(*pursuers) != team)
+                    .flat_map(|pursuers| sim.world.rosters[&pursuers].characters())
+                    .map(|(_, pursuer)| sim.stats_of(sim.world.active_characters.get(pursuer)).run)
+                    .max()
+                    .unwrap_or(0);
+                let outcome = stat_check(sim.rng, run, pursuit);
+                vec![Event::FleeResult { character: *character, outcome }]
+            }
+            _ => self.apply(&mut sim.world, &sim.database)
+        };
+        (ExecutedEvent(self), events)
+    }
+    /// Makes the event's changes to the game, returning the events that follow from it. Anything rolled for is left to `execute`.
+    fn apply(&self, world: &mut Game, database: &DatabaseManager) -> Vec<Event> {
         let mut events = Vec::new();
-        match &self {
+        match self {
+            Event::StartTurn { team, character } => {
+                world.turn += 1;
+                world.reactions.clear();
+                // Brings the team to the front first, in case anyone ahead of them was passed over
+                if let Some(index) = world.turn_order.iter().position(|queued| queued == team) {
+                    world.turn_order.rotate_left(index);
+                }
+                world.turn_order.rotate_right(1);
+                if let Some(roster) = world.rosters.get_mut(team) {
+                    roster.previous_turn = roster.position_of(*character).unwrap_or(roster.previous_turn);
+                }
+                events.extend(world.expire_summons());
+                events.extend(world.recover(*character));
+            }
             Event::Attack { target, .. } => {
                 events.push(Event::ProgressProgressBar { location: GameProgressBarLocation::Character(*target, ProgressBarName::HP), amount: 2 });
             }
             Event::CreateProgressBar { location, bar } => {
-               sim.world.insert_progress_bar(*location, *bar);
+               world.insert_progress_bar(*location, *bar);
             }
             Event::ProgressProgressBar { location, amount } => {
-                let bar =sim.world.get_progress_bar_mut(*location);
+                let bar = world.get_progress_bar_mut(*location);
+                let (was_complete, before) = (bar.complete(), bar.progress);
                 bar.increment(*amount);
+                let (complete, after) = (bar.complete(), bar.progress);
+                if let GameProgressBarLocation::Character(character, ProgressBarName::HP) = location {
+                    if let Some(fight) = world.boss.as_ref().filter(|fight| fight.boss == *character) {
+                        events.extend(fight.phases_crossed(before, after).map(|phase| Event::EnterPhase { boss: *character, phase }));
+                    }
+                    if !was_complete && complete {
+                        events.push(Event::Downed { character: *character });
+                    }
+                }
+            }
+            Event::RegressProgressBar { location, amount } => {
+                world.get_progress_bar_mut(*location).decrement(*amount);
+            }
+            Event::SwapPositions { team, first, second } => {
+                if let Some(roster) = world.rosters.get_mut(team) {
+                    roster.swap(*first, *second);
+                }
+            }
+            Event::MovePosition { character, position } => {
+                if let Some(team) = world.team_of(*character) {
+                    world.rosters.get_mut(&team).unwrap().move_character(*character, *position);
+                }
+            }
+            Event::LeaveRoster { character } | Event::Expire { character } => {
+                if let Some(team) = world.team_of(*character) {
+                    world.rosters.get_mut(&team).unwrap().remove_character(*character);
+                }
+            }
+            Event::Downed { character } => {
+                if let Some(team) = world.team_of(*character) {
+                    world.rosters.get_mut(&team).unwrap().remove_character(*character);
+                    world.downed.entry(team).or_default().push(*character);
+                }
+            }
+            Event::HazardResult { hazard, target, outcome } => {
+                events.extend(hazard.consequences(*target, *outcome));
+            }
+            Event::ApplyStatus { character, status } => {
+                world.active_characters.get_mut(*character).statuses.insert(*status);
+            }
+            Event::ClearStatus { character, status } => {
+                world.active_characters.get_mut(*character).statuses.remove(status);
+            }
+            Event::Rest { character, full } => {
+                let healing = if *full { FULL_REST_HEALING } else { CAMP_HEALING };
+                events.push(Event::RegressProgressBar { location: GameProgressBarLocation::Character(*character, ProgressBarName::HP), amount: healing });
+
+                let active_character = world.active_characters.get(*character);
+                events.extend(active_character.statuses.iter()
+                    .filter(|status| status.cured_by_rest(*full))
+                    .map(|status| Event::ClearStatus { character: *character, status: *status }));
+                events.extend(world.modifiers_of(active_character).iter().flat_map(|modifier| modifier.on_rest(active_character)));
+            }
+            Event::FleeResult { character, outcome } => {
+                let hit = Event::ProgressProgressBar { location: GameProgressBarLocation::Character(*character, ProgressBarName::HP), amount: 1 };
+                match outcome {
+                    CheckOutcome::Success | CheckOutcome::CriticalSuccess => {
+                        if let Some(team) = world.team_of(*character) {
+                            events.push(Event::Escaped { character: *character, team });
+                        }
+                    }
+                    CheckOutcome::Failure => events.push(hit),
+                    CheckOutcome::CriticalFailure => {
+                        events.push(hit);
+                        events.push(Event::ApplyStatus { character: *character, status: Status::Stunned });
+                    }
+                }
+            }
+            Event::Escaped { character, team } => {
+                if let Some(roster) = world.rosters.get_mut(team) {
+                    roster.remove_character(*character);
+                }
+                world.escaped.entry(*team).or_default().push(*character);
             }
-            Event::Say(_) => ()
+            Event::Retreat { team } => {
+                if let Some(roster) = world.rosters.get(team) {
+                    events.extend(roster.characters().map(|(_, character)| Event::Flee { character }));
+                }
+            }
+            Event::SpawnMonster { character, monster, team } => {
+                if world.rosters.get(team).is_some_and(|roster| !roster.is_full()) {
+                    let active_character = ActiveCharacter { id: *character, ..ActiveCharacter::new(Combatant::Monster(*monster), world.monster(*monster, database)) };
+                    events.extend(world.spawn(active_character, *team));
+                }
+            }
+            Event::EnterPhase { boss, phase } => {
+                let team = world.team_of(*boss);
+                let active_boss = world.active_characters.get(*boss);
+                let fight = world.boss.as_mut().expect("Only bosses have phases");
+                fight.phase = Some(*phase);
+
+                let phase = &fight.phases[*phase];
+                let gained: Vec<_> = phase.modifiers.iter().map(|modifier| world.ids.modifier(active_boss.character, *modifier)).collect();
+                events.extend(gained.iter().flat_map(|modifier| modifier.on_enter(active_boss)));
+                if let Some(team) = team {
+                    events.extend(phase.adds.iter().map(|monster| Event::SpawnMonster { character: world.ids.character(), monster: *monster, team }));
+                }
+                let abilities = phase.abilities.clone();
+                fight.modifiers.extend(gained);
+
+                let active_boss = world.active_characters.get_mut(*boss);
+                // A boss that had no abilities before has no energy to spend on them yet
+                if !abilities.is_empty() && !active_boss.progress_bars.contains_key(&ProgressBarName::Energy) {
+                    events.push(Event::CreateProgressBar { location: GameProgressBarLocation::Character(*boss, ProgressBarName::Energy), bar: energy_bar() });
+                }
+                active_boss.profile.abilities.extend(abilities);
+            }
+            Event::DropLoot { team, item } => {
+                world.loot.push((*team, item.clone()));
+            }
+            Event::UseItem { user, item, target } => {
+                let used = world.team_of(*user).and_then(|team| {
+                    let pack = world.packs.get_mut(&team)?;
+                    let index = pack.iter().position(|packed| packed.id == *item)?;
+                    world.used.entry(team).or_default().push(*item);
+                    pack.remove(index).template.consumable
+                });
+                match used {
+                    Some(Consumable::Potion { healing }) => {
+                        events.push(Event::RegressProgressBar { location: GameProgressBarLocation::Character(*target, ProgressBarName::HP), amount: healing });
+                    }
+                    Some(Consumable::Bomb { damage }) => {
+                        if let Some(team) = world.team_of(*target) {
+                            events.extend(world.rosters[&team].characters()
+                                .map(|(_, character)| Event::ProgressProgressBar { location: GameProgressBarLocation::Character(character, ProgressBarName::HP), amount: damage }));
+                        }
+                    }
+                    Some(Consumable::Scroll) => {
+                        events.extend(world.active_characters.get(*target).statuses.iter()
+                            .map(|status| Event::ClearStatus { character: *target, status: *status }));
+                    }
+                    None => ()
+                }
+            }
+            Event::Summon { character, summoner, kind, turns } => {
+                let team = world.team_of(*summoner).filter(|team| !world.rosters[team].is_full());
+                if let Some(team) = team {
+                    let active_summoner = world.active_characters.get(*summoner);
+                    let (mut profile, stats) = match kind {
+                        SummonKind::Minion(monster) => {
+                            let profile = world.monster(*monster, database);
+                            let stats = profile.stats.clone();
+                            (profile, stats)
+                        }
+                        SummonKind::Clone => {
+                            let mut profile = active_summoner.profile.clone();
+                            profile.name = format!("{}'s clone", profile.name);
+                            (profile, active_summoner.stats.clone())
+                        }
+                    };
+                    // The summon owns its own copies of the modifiers, and equipment stays with the original
+                    let owner = Combatant::Summon(*character);
+                    profile.modifiers = profile.modifiers.iter().map(|modifier| world.ids.modifier(owner, modifier.1)).collect();
+                    profile.equipment.clear();
+
+                    world.summons.insert(*character, Summon { turns_left: *turns });
+                    let active_character = ActiveCharacter { id: *character, stats, ..ActiveCharacter::new(owner, profile) };
+                    events.extend(world.spawn(active_character, team));
+                }
+            }
+            Event::AddModifier { character, modifier, permanent } => {
+                let modifier = world.ids.modifier(world.active_characters.get(*character).character, *modifier);
+                let active_character = world.active_characters.get_mut(*character);
+                active_character.gained.push(modifier);
+                if let (Combatant::Character(id), true) = (active_character.character, *permanent) {
+                    world.modifier_changes.push((id, ModifierChange::Gained(modifier)));
+                }
+                events.extend(modifier.on_enter(world.active_characters.get(*character)));
+            }
+            Event::RemoveModifier { character, modifier, permanent } => {
+                let active_character = world.active_characters.get_mut(*character);
+                active_character.gained.retain(|gained| gained.1 != *modifier);
+                if !active_character.lost.contains(modifier) {
+                    active_character.lost.push(*modifier);
+                }
+                if let (Combatant::Character(id), true) = (active_character.character, *permanent) {
+                    world.modifier_changes.push((id, ModifierChange::Lost(*modifier)));
+                }
+            }
+            Event::UseAbility { user, ability, target } => {
+                // The tick at the start of the user's next turn doesn't count towards the cooldown
+                if ability.cooldown > 0 {
+                    world.active_characters.get_mut(*user).cooldowns.insert(ability.name.clone(), ability.cooldown.saturating_add(1));
+                }
+                if let Some((bar, cost)) = ability.cost {
+                    events.push(Event::ProgressProgressBar { location: GameProgressBarLocation::Character(*user, bar), amount: cost });
+                }
+                let targets: Vec<_> = match ability.targeting {
+                    Targeting::EnemyTeam => world.team_of(*target).into_iter()
+                        .flat_map(|team| world.rosters[&team].characters().map(|(_, character)| character))
+                        .collect(),
+                    Targeting::Enemy | Targeting::Ally | Targeting::User => vec![*target]
+                };
+                events.extend(targets.into_iter().flat_map(|target| ability.effects.iter().map(|effect| effect.event(target, &mut world.ids)).collect::<Vec<_>>()));
+            }
+            Event::Hazard { .. } | Event::Flee { .. } | Event::Say(_) => ()
         }
-        (ExecutedEvent(self), events)
+        events
     }
 }
 
@@ -69,9 +434,9 @@ impl Event {
         
         let (event, outcomes) = self.execute(sim);
 
-        let outcomes = outcomes.into_iter().map(|event| event.complete(sim)).collect();
+        let outcomes: Vec<_> = outcomes.into_iter().map(|event| event.complete(sim)).collect();
 
-        let post_responses = sim.get_post_responses(&event)
+        let post_responses = sim.get_post_responses(&event, &outcomes)
             .into_iter()
             .map(|event| event.complete(sim))
             .collect();
@@ -80,13 +445,30 @@ impl Event {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExecutedEvent(pub Event);
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CompletedEvent {
     pub event: ExecutedEvent,
     pub pre_responses: Vec<CompletedEvent>,
     pub outcomes: Vec<CompletedEvent>,
     pub post_responses: Vec<CompletedEvent>
+}
+impl CompletedEvent {
+    /// Makes the same changes to the game that completing the event did, in the same order, without rolling anything or asking modifiers to respond
+    pub fn replay(&self, world: &mut Game, database: &DatabaseManager) {
+        self.pre_responses.iter().for_each(|event| event.replay(world, database));
+        self.event.0.apply(world, database);
+        self.outcomes.iter().for_each(|event| event.replay(world, database));
+        self.post_responses.iter().for_each(|event| event.replay(world, database));
+    }
+    /// The HP damage the character took from this event and its outcomes
+    pub fn damage_to(&self, character: ActiveCharacterID) -> u16 {
+        let own = match self.event.0 {
+            Event::ProgressProgressBar { location: GameProgressBarLocation::Character(target, ProgressBarName::HP), amount } if target == character => amount,
+            _ => 0
+        };
+        own + self.outcomes.iter().map(|outcome| outcome.damage_to(character)).sum::<u16>()
+    }
 }
\ No newline at end of file
@@ -1,15 +1,18 @@
-use std::{collections::{HashMap, VecDeque}, ops::{Index, IndexMut}};
+use std::{collections::{BTreeMap, BTreeSet, HashMap, VecDeque}, ops::{Index, IndexMut}};
 
 use chronobase::{EntityID, SavableEntity};
-use colored::Colorize;
-use rand::seq::{IteratorRandom, SliceRandom};
+use itertools::Itertools;
+use rand::{seq::{IteratorRandom, SliceRandom}, Rng};
 use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{database::{ActiveCharacterManager, DatabaseManager}, delver_display::ToDisplayConstruct, entities::{Character, Team}, events::{CompletedEvent, Event}, progress_bars::{health_bar, GameProgressBarLocation, ProgressBar, ProgressBarName}};
+use crate::{bosses::{Boss, BossFight}, database::{ActiveCharacterManager, DatabaseManager}, delve::Delve, delver_display::ToDisplayConstruct, entities::{Character, Combatant, Monster, Profile, Stats, Team}, events::{CompletedEvent, Event}, items::{Item, ItemTemplate}, modifiers::{Modifier, ModifierChange, ModifierID, ModifierType, REACTIONS_PER_TURN}, progress_bars::{boss_health_bar, energy_bar, health_bar, GameProgressBarLocation, ProgressBar, ProgressBarName}, records::{GameDay, GameMode, GameRecord}, statuses::Status, summons::Summon, turn_log::TurnLog};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// Bumped whenever a change to the sim means old games would play out differently
+pub const RULES_VERSION: u32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct ActiveCharacterID(Uuid);
 impl ActiveCharacterID {
     pub fn roll() -> Self {
@@ -17,21 +20,83 @@ impl ActiveCharacterID {
     }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+/// Hands out ids for everyone and everything in a game. They're counted rather than rolled, so a replay of the game hands out the same ones,
+/// and a game's namespace is drawn from its rng, so two games played from the same seed do too.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct IdSource {
+    namespace: u64,
+    issued: u64
+}
+impl Default for IdSource {
+    fn default() -> Self {
+        Self { namespace: rand::random(), issued: 0 }
+    }
+}
+impl IdSource {
+    pub fn seeded(rng: &mut ChaCha8Rng) -> Self {
+        Self { namespace: rng.gen(), issued: 0 }
+    }
+    pub fn uuid(&mut self) -> Uuid {
+        self.issued += 1;
+        Uuid::from_u64_pair(self.namespace, self.issued)
+    }
+    pub fn character(&mut self) -> ActiveCharacterID {
+        ActiveCharacterID(self.uuid())
+    }
+    pub fn modifier(&mut self, owner: Combatant, type_: ModifierType) -> Modifier {
+        Modifier(ModifierID(owner, self.uuid()), type_)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ActiveCharacter {
     pub id: ActiveCharacterID,
-    pub character: EntityID<Character>,
-    pub progress_bars: HashMap<ProgressBarName, ProgressBar>
+    pub character: Combatant,
+    /// The combatant as they were when they entered the game. Read instead of the database, so later edits
+    /// to the character don't change a game in progress or a replay of it.
+    pub profile: Profile,
+    pub progress_bars: HashMap<ProgressBarName, ProgressBar>,
+    pub statuses: BTreeSet<Status>,
+    /// Base stats plus equipment, worked out when the character enters the game
+    pub stats: Stats,
+    /// Granted by equipment for as long as the character is in the game
+    pub granted: Vec<Modifier>,
+    /// Turns left before each ability can be used again, by ability name. Ticked at the start of each of the character's turns,
+    /// so an ability is ready again on the turn this reaches 0.
+    pub cooldowns: BTreeMap<String, u8>,
+    /// Picked up during the game, on top of their own
+    pub gained: Vec<Modifier>,
+    /// Taken away during the game. Hides any of their own modifiers, or ones granted by equipment, of these types.
+    pub lost: Vec<ModifierType>
 }
 impl ActiveCharacter {
-    pub fn new(character: EntityID<Character>) -> Self {
-        Self { id: ActiveCharacterID::roll(), character, progress_bars: HashMap::default() }
+    pub fn new(character: Combatant, profile: Profile) -> Self {
+        let stats = profile.stats.clone();
+        Self { id: ActiveCharacterID::roll(), character, profile, progress_bars: HashMap::default(), statuses: BTreeSet::new(), stats, granted: Vec::new(), cooldowns: BTreeMap::new(), gained: Vec::new(), lost: Vec::new() }
+    }
+    pub fn has_status(&self, status: Status) -> bool {
+        self.statuses.contains(&status)
+    }
+    pub fn is_summon(&self) -> bool {
+        matches!(self.character, Combatant::Summon(_))
+    }
+    /// Builds a new active character, with their equipment applied
+    pub fn load(combatant: Combatant, database: &DatabaseManager, ids: &mut IdSource) -> Self {
+        let profile: Profile = database.load(combatant);
+        let equipment = profile.equipment.clone();
+        let mut active_character = ActiveCharacter { id: ids.character(), ..ActiveCharacter::new(combatant, profile) };
+        for item in equipment {
+            let item: Item = database.load(item);
+            item.template.apply_bonuses(&mut active_character.stats);
+            active_character.granted.extend(item.template.modifiers.iter().map(|type_| ids.modifier(combatant, *type_)));
+        }
+        active_character
     }
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Roster {
-    pub characters: [Option<ActiveCharacterID>; 6],
+    characters: [Option<ActiveCharacterID>; 6],
     pub previous_turn: Position
 }
 impl Roster {
@@ -44,18 +109,29 @@ impl Roster {
     pub fn get(&self, position: Position) -> Option<ActiveCharacterID> {
         self.characters[position]
     }
+    /// The filled positions of the roster, in canonical order
+    pub fn characters(&self) -> impl Iterator<Item = (Position, ActiveCharacterID)> + '_ {
+        Position::canonical_order().into_iter().flat_map(|position| self.get(position).map(|character| (position, character)))
+    }
+    pub fn position_of(&self, character: ActiveCharacterID) -> Option<Position> {
+        self.characters().find(|(_, c)| *c == character).map(|(position, _)| position)
+    }
+    pub fn contains(&self, character: ActiveCharacterID) -> bool {
+        self.position_of(character).is_some()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.characters.iter().all(Option::is_none)
+    }
+    pub fn is_full(&self) -> bool {
+        self.characters.iter().all(Option::is_some)
+    }
 
-    pub fn next_filled(&mut self) -> Option<ActiveCharacterID> {
-        for position in self.previous_turn.single_canonical_cycle() {
-            if let Some(character) = self.get(position) {
-                self.previous_turn = position;
-                return Some(character)
-            }
-        }
-        None
+    /// Whoever's turn is next, going round from whoever had the previous one
+    pub fn next_filled(&self) -> Option<ActiveCharacterID> {
+        self.previous_turn.single_canonical_cycle().find_map(|position| self.get(position))
     }
 
-    pub fn random_filled(&mut self, rng: &mut ChaCha8Rng) -> Option<ActiveCharacterID> {
+    pub fn random_filled(&self, rng: &mut ChaCha8Rng) -> Option<ActiveCharacterID> {
         let mut order = Position::enter_order();
         order.shuffle(rng);
         order.into_iter().flat_map(|p| self.get(p))
@@ -72,6 +148,35 @@ impl Roster {
         }
         None
     }
+
+    /// Empties the given position, returning whoever was in it
+    pub fn remove(&mut self, position: Position) -> Option<ActiveCharacterID> {
+        self.characters[position].take()
+    }
+
+    /// Removes the given character from the roster, returning the position they were in
+    pub fn remove_character(&mut self, character: ActiveCharacterID) -> Option<Position> {
+        let position = self.position_of(character)?;
+        self.remove(position);
+        Some(position)
+    }
+
+    /// Swaps the occupants of two positions. Either position may be empty.
+    pub fn swap(&mut self, first: Position, second: Position) {
+        self.characters.swap(first.into(), second.into());
+    }
+
+    /// Moves the given character to the given position, swapping with whoever is already there.
+    /// Returns the position the character moved from, or None if they aren't on this roster.
+    pub fn move_character(&mut self, character: ActiveCharacterID, position: Position) -> Option<Position> {
+        let from = self.position_of(character)?;
+        self.swap(from, position);
+        Some(from)
+    }
+
+    pub fn has_duplicates(&self) -> bool {
+        self.characters().map(|(_, character)| character).duplicates().next().is_some()
+    }
 }
 impl Default for Roster {
     fn default() -> Self {
@@ -154,39 +259,179 @@ impl TryFrom<usize> for Position {
     }
 }
 
-#[derive(Default, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct Game {
     pub game_id: EntityID<Game>,
+    /// Where each turn's events are recorded
+    pub log: EntityID<TurnLog>,
+    /// How many turns have started
+    pub turn: u32,
+    /// The turn the game's log picks up from. Forks start partway through.
+    pub started_at: u32,
+    /// The game this one was forked from, and the turn it was forked at
+    pub forked_from: Option<(EntityID<Game>, u32)>,
+    /// The seed of the rng the game started with
+    pub seed: [u8; 32],
+    /// How far into the seed's stream the rng was when the game started
+    pub stream_position: u128,
+    /// The rules the game was played under
+    pub rules_version: u32,
+    /// Monsters that can join partway through, as they were when the game started
+    pub monsters: HashMap<EntityID<Monster>, Profile>,
+    pub record: GameRecord,
+    /// Whether the game's consequences have been written back yet
+    #[serde(default)]
+    pub settled: bool,
+    pub ids: IdSource,
     pub active_characters: ActiveCharacterManager,
     pub turn_order: VecDeque<EntityID<Team>>,
     pub rosters: HashMap<EntityID<Team>, Roster>,
+    pub progress_bars: HashMap<ProgressBarName, ProgressBar>,
+    pub boss: Option<BossFight>,
+    /// Characters who fled the game, by the team they fled from
+    pub escaped: HashMap<EntityID<Team>, Vec<ActiveCharacterID>>,
+    /// Characters who were downed, by the team they were on
+    pub downed: HashMap<EntityID<Team>, Vec<ActiveCharacterID>>,
+    /// Items found this game, waiting to be handed to the team that found them
+    pub loot: Vec<(EntityID<Team>, ItemTemplate)>,
+    /// The consumables each team brought into the game and hasn't used yet
+    pub packs: HashMap<EntityID<Team>, Vec<Item>>,
+    /// Consumables used up this game, waiting to be taken out of their team's inventory
+    pub used: HashMap<EntityID<Team>, Vec<EntityID<Item>>>,
+    /// How many times each character has reacted this turn
+    pub reactions: HashMap<ActiveCharacterID, u8>,
+    pub summons: HashMap<ActiveCharacterID, Summon>,
+    /// Modifier changes to write back to characters once the game is over
+    pub modifier_changes: Vec<(EntityID<Character>, ModifierChange)>,
 
-    pub latest_events: Vec<CompletedEvent>
+    /// Everything that happened in the latest turn, then anything done since, such as swapping positions between turns
+    pub latest_events: Vec<CompletedEvent>,
+    /// How many of the latest events came from the latest turn itself. The rest are logged with the next one.
+    #[serde(default)]
+    pub turn_events: usize
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameOutcome {
+    /// Everyone else was downed
+    Victory(EntityID<Team>),
+    /// The team was beaten, but got at least some of its characters away
+    Escape(EntityID<Team>),
+    /// Nobody is left standing
+    Draw
 }
+
 impl Game {
-    pub fn insert_progress_bar(&mut self, location: GameProgressBarLocation, bar: ProgressBar) -> Option<ProgressBar> {
+    /// None while more than one team is still standing
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        let mut standing = self.standing_teams();
+        let winner = standing.next();
+        if standing.next().is_some() {
+            return None
+        }
+        let escaped = self.turn_order.iter()
+            .find(|team| Some(**team) != winner && self.escaped.get(team).is_some_and(|escaped| !escaped.is_empty()));
+        Some(match (winner, escaped) {
+            (_, Some(team)) => GameOutcome::Escape(*team),
+            (Some(team), None) => GameOutcome::Victory(team),
+            (None, None) => GameOutcome::Draw
+        })
+    }
+    /// Teams that still have someone on their roster
+    pub fn standing_teams(&self) -> impl Iterator<Item = EntityID<Team>> + '_ {
+        self.turn_order.iter().copied().filter(|team| !self.rosters[team].is_empty())
+    }
+    /// Whose turn is next. Teams with nobody left standing keep their place in the turn order, but are passed over.
+    pub fn next_team(&self) -> Option<EntityID<Team>> {
+        self.turn_order.iter().take(1).chain(self.turn_order.iter().skip(1).rev())
+            .copied()
+            .find(|team| !self.rosters[team].is_empty())
+    }
+    /// Whether the character has a reaction left this turn, on top of the `pending` ones they've made that aren't recorded yet
+    pub fn can_react(&self, character: ActiveCharacterID, pending: u8) -> bool {
+        self.reactions.get(&character).copied().unwrap_or_default().saturating_add(pending) < REACTIONS_PER_TURN
+    }
+    pub fn record_reactions(&mut self, characters: impl IntoIterator<Item = ActiveCharacterID>) {
+        for character in characters {
+            *self.reactions.entry(character).or_default() += 1;
+        }
+    }
+    /// The team whose roster the given character is currently on
+    pub fn team_of(&self, character: ActiveCharacterID) -> Option<EntityID<Team>> {
+        self.rosters.iter().find(|(_, roster)| roster.contains(character)).map(|(team, _)| *team)
+    }
+    /// A boss's HP is kept on the game rather than on the boss, so that's where their HP location leads
+    fn resolve(&self, location: GameProgressBarLocation) -> GameProgressBarLocation {
         match location {
+            GameProgressBarLocation::Character(character, ProgressBarName::HP) if self.boss.as_ref().is_some_and(|fight| fight.boss == character) => {
+                GameProgressBarLocation::Game(ProgressBarName::BossHP)
+            }
+            location => location
+        }
+    }
+    pub fn insert_progress_bar(&mut self, location: GameProgressBarLocation, bar: ProgressBar) -> Option<ProgressBar> {
+        match self.resolve(location) {
             GameProgressBarLocation::Character(character, name) => {
                 let character = self.active_characters.get_mut(character);
                 character.progress_bars.insert(name, bar)
             }
+            GameProgressBarLocation::Game(name) => self.progress_bars.insert(name, bar)
         }
     }
     pub fn get_progress_bar(&self, location: GameProgressBarLocation) -> &ProgressBar {
-        match location {
+        match self.resolve(location) {
             GameProgressBarLocation::Character(character, name) => {
                 let character = self.active_characters.get(character);
                 character.progress_bars.get(&name).unwrap()
             }
+            GameProgressBarLocation::Game(name) => self.progress_bars.get(&name).unwrap()
         }
     }
     pub fn get_progress_bar_mut(&mut self, location: GameProgressBarLocation) -> &mut ProgressBar {
-        match location {
+        match self.resolve(location) {
             GameProgressBarLocation::Character(character, name) => {
                 let character = self.active_characters.get_mut(character);
                 character.progress_bars.get_mut(&name).unwrap()
             }
+            GameProgressBarLocation::Game(name) => self.progress_bars.get_mut(&name).unwrap()
+        }
+    }
+    /// The monster as it was when the game started, or as it is now if it wasn't expected then
+    pub fn monster(&self, monster: EntityID<Monster>, database: &DatabaseManager) -> Profile {
+        self.monsters.get(&monster).cloned().unwrap_or_else(|| database.load(Combatant::Monster(monster)))
+    }
+    /// The modifiers behind an active character, along with any they've picked up or lost during this game
+    pub fn modifiers_of(&self, active_character: &ActiveCharacter) -> Vec<Modifier> {
+        let mut modifiers = active_character.profile.modifiers.clone();
+        modifiers.extend(active_character.granted.iter().copied());
+        modifiers.retain(|modifier| !active_character.lost.contains(&modifier.1));
+        modifiers.extend(active_character.gained.iter().copied());
+        if let Some(fight) = self.boss.as_ref().filter(|fight| fight.boss == active_character.id) {
+            modifiers.extend(fight.modifiers.iter().copied());
         }
+        modifiers
+    }
+    /// Puts an already active character onto the team's roster, as is. No entry events are triggered.
+    pub fn insert_active_character(&mut self, active_character: ActiveCharacter, team:EntityID<Team>) -> ActiveCharacterID {
+        let active_id = active_character.id;
+        self.rosters.get_mut(&team).unwrap().add_character(active_id).unwrap();
+        self.active_characters.add_active_character(active_character);
+        active_id
+    }
+    /// Puts a new active character onto the team's roster, returning the events for them entering the game
+    pub(crate) fn spawn(&mut self, active_character: ActiveCharacter, team:EntityID<Team>) -> Vec<Event> {
+        let active_id = self.insert_active_character(active_character, team);
+        let bar = match &self.boss {
+            Some(fight) if fight.boss == active_id => boss_health_bar(fight.hp),
+            _ => health_bar()
+        };
+
+        let mut events = vec![Event::CreateProgressBar { location: GameProgressBarLocation::Character(active_id, ProgressBarName::HP), bar }];
+        let active_character = self.active_characters.get(active_id);
+        if !active_character.profile.abilities.is_empty() {
+            events.push(Event::CreateProgressBar { location: GameProgressBarLocation::Character(active_id, ProgressBarName::Energy), bar: energy_bar() });
+        }
+        events.extend(self.modifiers_of(active_character).iter().flat_map(|modifier| modifier.on_enter(active_character)));
+        events
     }
 }
 
@@ -194,77 +439,181 @@ pub struct Sim<'a> {
     pub database: DatabaseManager,
     pub rng: &'a mut ChaCha8Rng,
 
-    pub world: Game
+    pub world: Game,
+    pub delve: Option<Delve>,
+    /// Whether events are printed as they complete
+    pub log_events: bool,
+    /// Whether turns are saved to the game's log as they're played
+    pub save_turns: bool
 }
 impl<'a> Sim<'a> {
-    pub fn new(database: DatabaseManager, rng: &'a mut ChaCha8Rng, delve_team: EntityID<Team>, defender_team: EntityID<Team>) -> Self {
+    /// The day is where the game falls in the league's calendar, if it does
+    pub fn new(database: DatabaseManager, rng: &'a mut ChaCha8Rng, delve_team: EntityID<Team>, defender_team: EntityID<Team>, day: Option<GameDay>) -> Self {
         let mut result = Sim {
             database,
             rng,
             world: Game::default(),
+            delve: None,
+            log_events: true,
+            save_turns: true
         };
+        result.world.record = GameRecord::starting(GameMode::Arena, day);
+        result.world.ids = IdSource::seeded(result.rng);
         result.add_team(delve_team);
         result.add_team(defender_team);
 
         result
     }
+    /// Whether at most one team is left standing
+    pub fn finished(&self) -> bool {
+        self.world.standing_teams().nth(1).is_none()
+    }
+    pub fn winner(&self) -> Option<EntityID<Team>> {
+        let mut standing = self.world.standing_teams();
+        standing.next().filter(|_| standing.next().is_none())
+    }
     pub fn turn(&mut self) {
+        if self.world.turn == self.world.started_at && self.save_turns {
+            self.record_start();
+        }
+        // Anything done since the last turn is logged with this one
+        let between = self.world.latest_events.split_off(self.world.turn_events);
         self.world.latest_events.clear();
 
-        let attacking_team = *self.world.turn_order.front().expect("There is always a team");
-        let defending_team = 
-            *(1..self.world.turn_order.len()).choose(&mut self.rng)
-                .and_then(|index| self.world.turn_order.get(index))
-                .expect("There is always a second team");
-        self.world.turn_order.rotate_right(1);
-
-        let attacker = self.world.rosters.get_mut(&attacking_team).unwrap().next_filled().expect("Roster should not be empty");
-        let defender = self.world.rosters.get_mut(&defending_team).unwrap().random_filled(self.rng).expect("Roster should not be empty");
-        
-        let attacking_character = self.database.load(self.world.active_characters.get(attacker).character);
-        let events = attacking_character.modifiers().flat_map(|modifier| modifier.start_turn(self.world.active_characters.get(attacker))).collect::<Vec<_>>();
+        let attacking_team = self.world.next_team().expect("There is always a team");
+        let defending_team = self.world.standing_teams()
+            .filter(|team| *team != attacking_team)
+            .choose(&mut self.rng)
+            .expect("There is always a second team");
+
+        let attacker = self.world.rosters[&attacking_team].next_filled().expect("Roster should not be empty");
+        self.complete_events(vec![Event::StartTurn { team: attacking_team, character: attacker }]);
+
+        let attacking_character = self.world.active_characters.get(attacker);
+        let events = self.modifiers_of(attacking_character).iter().flat_map(|modifier| modifier.start_turn(attacking_character)).collect::<Vec<_>>();
         self.complete_events(events);
 
-        let event = Event::Attack { attacker, target: defender };
-        self.complete_events(vec![event]);
+        let active_attacker = self.world.active_characters.get(attacker);
+        let stunned = active_attacker.has_status(Status::Stunned);
+        let events = active_attacker.statuses.iter().flat_map(|status| status.start_turn(active_attacker)).collect::<Vec<_>>();
+        self.complete_events(events);
+
+        // Statuses can knock the attacker out before they get to act, and summons fading away can leave nobody to defend
+        let defender = self.world.rosters[&defending_team].random_filled(self.rng);
+        if let Some(defender) = defender.filter(|_| !stunned && self.world.rosters[&attacking_team].contains(attacker)) {
+            let event = self.choose_action(attacking_team, attacker, defender);
+            self.complete_events(vec![event]);
+        }
 
-        self.database.save(self.world.clone());
+        self.world.turn_events = self.world.latest_events.len();
+        if self.save_turns {
+            self.record_turn(between);
+        }
+    }
+    /// What the attacker does with their turn
+    fn choose_action(&self, attacking_team: EntityID<Team>, attacker: ActiveCharacterID, defender: ActiveCharacterID) -> Event {
+        if self.wants_to_flee(attacking_team) {
+            Event::Retreat { team: attacking_team }
+        } else if let Some((item, target)) = self.choose_item(attacking_team, defender) {
+            Event::UseItem { user: attacker, item, target }
+        } else if let Some((ability, target)) = self.choose_ability(attacking_team, attacker, defender) {
+            Event::UseAbility { user: attacker, ability, target }
+        } else {
+            Event::Attack { attacker, target: defender }
+        }
     }
-    fn complete_events(&mut self, events:Vec<Event>) {
+    pub(crate) fn complete_events(&mut self, events:Vec<Event>) {
         for event in events {
             let completed_event = event.complete(self);
-            println!("{}", completed_event.longform(&self.world, &self.database));
+            if self.log_events {
+                println!("{}", completed_event.longform(&self.world, &self.database));
+            }
             self.world.latest_events.push(completed_event);
         }
     }
     pub fn add_team(&mut self, team:EntityID<Team>) -> EntityID<Team> {
         let team = self.database.load(team);
-        self.world.turn_order.push_front(team.id);
-        self.world.rosters.insert(team.id, Roster::new());
+        self.add_empty_team(team.id);
+        self.pack(team.id);
 
         team.roster.into_iter().take(6).for_each(|c| { self.add_character(c, team.id); });
 
         team.id
     }
+    /// Gives the team a place in the turn order and an empty roster
+    pub fn add_empty_team(&mut self, team:EntityID<Team>) {
+        self.world.turn_order.push_front(team);
+        self.world.rosters.insert(team, Roster::new());
+    }
     pub fn add_character(&mut self, id:EntityID<Character>, team:EntityID<Team>) -> ActiveCharacterID {
-        let active_character = ActiveCharacter::new(id);
+        self.add_combatant(Combatant::Character(id), team)
+    }
+    pub fn add_monster(&mut self, id:EntityID<Monster>, team:EntityID<Team>) -> ActiveCharacterID {
+        self.add_combatant(Combatant::Monster(id), team)
+    }
+    /// Spawns the monsters onto a new defending roster. The roster's team id is never saved to the database.
+    pub fn add_monsters(&mut self, monsters: &[EntityID<Monster>]) -> EntityID<Team> {
+        let team = EntityID::from(self.world.ids.uuid());
+        self.add_empty_team(team);
+        monsters.iter().take(6).for_each(|monster| { self.add_monster(*monster, team); });
+        team
+    }
+    fn add_combatant(&mut self, combatant: Combatant, team:EntityID<Team>) -> ActiveCharacterID {
+        let active_character = self.active_character(combatant);
         let active_id = active_character.id;
-        self.world.rosters.get_mut(&team).unwrap().add_character(active_character.id).unwrap();
-        self.world.active_characters.add_active_character(active_character);
-
-        let character = self.database.load(id);
-        self.complete_events(vec![Event::CreateProgressBar { location: GameProgressBarLocation::Character(active_id, ProgressBarName::HP), bar: health_bar() }]);
-        let events = character.modifiers().flat_map(|modifier| modifier.on_enter(self.world.active_characters.get(active_id))).collect::<Vec<_>>();
+        let events = self.spawn(active_character, team);
         self.complete_events(events);
         active_id
     }
+    /// Spawns the boss onto a new defending roster, with its health on the game. The roster's team id is never saved to the database.
+    pub fn add_boss(&mut self, boss: &Boss) -> EntityID<Team> {
+        let team = EntityID::from(self.world.ids.uuid());
+        self.add_empty_team(team);
+        let active_character = self.active_character(Combatant::Monster(boss.monster));
+        self.world.boss = Some(BossFight::new(active_character.id, boss));
+        let events = self.spawn(active_character, team);
+        self.complete_events(events);
+        team
+    }
+    /// Builds a new active character, with their equipment applied
+    pub fn active_character(&mut self, combatant: Combatant) -> ActiveCharacter {
+        ActiveCharacter::load(combatant, &self.database, &mut self.world.ids)
+    }
+    pub(crate) fn spawn(&mut self, active_character: ActiveCharacter, team:EntityID<Team>) -> Vec<Event> {
+        self.world.spawn(active_character, team)
+    }
+    pub fn modifiers_of(&self, active_character: &ActiveCharacter) -> Vec<Modifier> {
+        self.world.modifiers_of(active_character)
+    }
+    pub fn stats_of(&self, active_character: &ActiveCharacter) -> Stats {
+        active_character.stats.clone()
+    }
+    pub fn insert_active_character(&mut self, active_character: ActiveCharacter, team:EntityID<Team>) -> ActiveCharacterID {
+        self.world.insert_active_character(active_character, team)
+    }
+    pub fn swap_positions(&mut self, team: EntityID<Team>, first: Position, second: Position) {
+        self.complete_events(vec![Event::SwapPositions { team, first, second }]);
+    }
+    pub fn move_character(&mut self, character: ActiveCharacterID, position: Position) {
+        self.complete_events(vec![Event::MovePosition { character, position }]);
+    }
+    pub fn remove_character(&mut self, character: ActiveCharacterID) {
+        self.complete_events(vec![Event::LeaveRoster { character }]);
+    }
+    /// Gives the character the modifier for the rest of the game, and for good if it's permanent
+    pub fn add_modifier(&mut self, character: ActiveCharacterID, modifier: ModifierType, permanent: bool) {
+        self.complete_events(vec![Event::AddModifier { character, modifier, permanent }]);
+    }
+    /// Takes modifiers of the type away from the character for the rest of the game, and for good if it's permanent
+    pub fn remove_modifier(&mut self, character: ActiveCharacterID, modifier: ModifierType, permanent: bool) {
+        self.complete_events(vec![Event::RemoveModifier { character, modifier, permanent }]);
+    }
     pub fn display(&self, delve_team: EntityID<Team>, defender_team: EntityID<Team>) {
         for team in [delve_team, defender_team] {
-            let team = self.database.load(team);
-            println!("{}", team.name.color(team.colour));
-            let roster = self.world.rosters.get(&team.id).unwrap();
+            println!("{}", team.shortform(&self.world, &self.database));
+            let roster = self.world.rosters.get(&team).unwrap();
             for i in Position::canonical_order() {
-                if let Some(character) = roster.characters[i] {
+                if let Some(character) = roster.get(i) {
                     let character = self.world.active_characters.get(character);
                     println!("{}", character.longform(&self.world, &self.database));
                 }
@@ -275,4 +624,69 @@ impl<'a> Sim<'a> {
 
 impl SavableEntity for Game {
     const TABLE_NAME: &'static str = "game";
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roster_of(n: usize) -> (Roster, Vec<ActiveCharacterID>) {
+        let mut roster = Roster::new();
+        let characters: Vec<_> = (0..n).map(|_| ActiveCharacterID::roll()).collect();
+        for character in &characters {
+            roster.add_character(*character);
+        }
+        (roster, characters)
+    }
+
+    #[test]
+    fn characters_fill_the_roster_in_enter_order() {
+        let (mut roster, characters) = roster_of(6);
+        assert_eq!(roster.characters().count(), 6);
+        for (position, character) in Position::enter_order().into_iter().zip(&characters) {
+            assert_eq!(roster.get(position), Some(*character));
+        }
+        assert_eq!(roster.add_character(ActiveCharacterID::roll()), None);
+    }
+
+    #[test]
+    fn remove_empties_the_position() {
+        let (mut roster, characters) = roster_of(2);
+        assert_eq!(roster.remove(Position::Up), Some(characters[1]));
+        assert_eq!(roster.remove(Position::Up), None);
+        assert_eq!(roster.remove_character(characters[0]), Some(Position::Top));
+        assert_eq!(roster.remove_character(characters[0]), None);
+        assert!(roster.is_empty());
+    }
+
+    #[test]
+    fn swap_exchanges_occupants() {
+        let (mut roster, characters) = roster_of(2);
+        roster.swap(Position::Top, Position::Up);
+        assert_eq!(roster.get(Position::Top), Some(characters[1]));
+        assert_eq!(roster.get(Position::Up), Some(characters[0]));
+
+        roster.swap(Position::Top, Position::Charm);
+        assert_eq!(roster.get(Position::Top), None);
+        assert_eq!(roster.get(Position::Charm), Some(characters[1]));
+    }
+
+    #[test]
+    fn move_character_swaps_with_whoever_is_there() {
+        let (mut roster, characters) = roster_of(2);
+        assert_eq!(roster.move_character(characters[0], Position::Up), Some(Position::Top));
+        assert_eq!(roster.position_of(characters[0]), Some(Position::Up));
+        assert_eq!(roster.position_of(characters[1]), Some(Position::Top));
+
+        assert_eq!(roster.move_character(characters[1], Position::Bottom), Some(Position::Top));
+        assert_eq!(roster.get(Position::Top), None);
+        assert_eq!(roster.move_character(ActiveCharacterID::roll(), Position::Top), None);
+    }
+
+    #[test]
+    fn has_duplicates_finds_a_character_in_two_positions() {
+        let (mut roster, characters) = roster_of(3);
+        assert!(!roster.has_duplicates());
+        roster.add_character(characters[0]);
+        assert!(roster.has_duplicates());
+    }
+}
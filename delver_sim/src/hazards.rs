@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{checks::CheckOutcome, entities::Stat, events::Event, game::ActiveCharacterID, progress_bars::{GameProgressBarLocation, ProgressBarName}, statuses::Status};
+
+/// Non-combat dangers, each testing one stat
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Hazard {
+    PitTrap,
+    GasRoom,
+    CollapsingCeiling
+}
+impl Hazard {
+    pub fn all() -> [Hazard; 3] {
+        [Hazard::PitTrap, Hazard::GasRoom, Hazard::CollapsingCeiling]
+    }
+    pub fn stat(&self) -> Stat {
+        match self {
+            Hazard::PitTrap => Stat::Buoyancy,
+            Hazard::GasRoom => Stat::Perpetuity,
+            Hazard::CollapsingCeiling => Stat::Realism
+        }
+    }
+    /// Whether the whole party is caught, rather than just one of them
+    pub fn hits_everyone(&self) -> bool {
+        match self {
+            Hazard::PitTrap => false,
+            Hazard::GasRoom | Hazard::CollapsingCeiling => true
+        }
+    }
+    pub fn consequences(&self, target: ActiveCharacterID, outcome: CheckOutcome) -> Vec<Event> {
+        let damage = |amount| Event::ProgressProgressBar { location: GameProgressBarLocation::Character(target, ProgressBarName::HP), amount };
+        let status = |status| Event::ApplyStatus { character: target, status };
+        match (self, outcome) {
+            (_, CheckOutcome::Success | CheckOutcome::CriticalSuccess) => Vec::new(),
+            (Hazard::PitTrap, CheckOutcome::Failure) => vec![damage(1)],
+            (Hazard::PitTrap, CheckOutcome::CriticalFailure) => vec![damage(2)],
+            (Hazard::GasRoom, CheckOutcome::Failure) => vec![status(Status::Poisoned)],
+            (Hazard::GasRoom, CheckOutcome::CriticalFailure) => vec![damage(1), status(Status::Poisoned)],
+            (Hazard::CollapsingCeiling, CheckOutcome::Failure) => vec![damage(1)],
+            (Hazard::CollapsingCeiling, CheckOutcome::CriticalFailure) => vec![damage(2), status(Status::Stunned)]
+        }
+    }
+    pub fn description(&self) -> &'static str {
+        match self {
+            Hazard::PitTrap => "A pit trap opens beneath",
+            Hazard::GasRoom => "Choking gas fills the room around",
+            Hazard::CollapsingCeiling => "The ceiling collapses on"
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::ops::{Index, IndexMut};
+
+use chronobase::{EntityID, SavableEntity};
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::{Stat, Stats, Team}, game::{ActiveCharacterID, Sim}, modifiers::ModifierType, progress_bars::ProgressBarName};
+
+/// How many consumables a team brings into each game
+const PACK_SIZE: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Slot {
+    Weapon,
+    Armour,
+    Trinket
+}
+impl Slot {
+    pub fn canonical_order() -> [Slot; 3] {
+        [Slot::Weapon, Slot::Armour, Slot::Trinket]
+    }
+}
+
+/// What a character has equipped in each slot
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    pub weapon: Option<EntityID<Item>>,
+    pub armour: Option<EntityID<Item>>,
+    pub trinket: Option<EntityID<Item>>
+}
+impl Index<Slot> for Equipment {
+    type Output = Option<EntityID<Item>>;
+    fn index(&self, index: Slot) -> &Self::Output {
+        match index {
+            Slot::Weapon => &self.weapon,
+            Slot::Armour => &self.armour,
+            Slot::Trinket => &self.trinket
+        }
+    }
+}
+impl IndexMut<Slot> for Equipment {
+    fn index_mut(&mut self, index: Slot) -> &mut Self::Output {
+        match index {
+            Slot::Weapon => &mut self.weapon,
+            Slot::Armour => &mut self.armour,
+            Slot::Trinket => &mut self.trinket
+        }
+    }
+}
+impl Equipment {
+    /// Everything equipped, in canonical slot order
+    pub fn items(&self) -> impl Iterator<Item = EntityID<Item>> + '_ {
+        Slot::canonical_order().into_iter().filter_map(|slot| self[slot])
+    }
+}
+
+/// What happens when an item is used up mid-game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Consumable {
+    /// Heals one character
+    Potion { healing: u16 },
+    /// Hits everyone on a team
+    Bomb { damage: u16 },
+    /// Clears every status from one character
+    Scroll
+}
+
+/// What an item is, apart from any particular copy of it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemTemplate {
+    pub name: String,
+    /// Where the item is worn. None for items that can't be equipped.
+    pub slot: Option<Slot>,
+    /// Added to the wearer's stats while equipped
+    #[serde(default)]
+    pub bonuses: Vec<(Stat, i8)>,
+    /// Granted to the wearer while equipped
+    #[serde(default)]
+    pub modifiers: Vec<ModifierType>,
+    /// None for items that can't be used up
+    pub consumable: Option<Consumable>
+}
+impl ItemTemplate {
+    pub fn new(name: String) -> Self {
+        Self { name, slot: None, bonuses: Vec::new(), modifiers: Vec::new(), consumable: None }
+    }
+    pub fn equipment(name: String, slot: Slot, bonuses: Vec<(Stat, i8)>, modifiers: Vec<ModifierType>) -> Self {
+        Self { slot: Some(slot), bonuses, modifiers, ..Self::new(name) }
+    }
+    pub fn consumable(name: String, consumable: Consumable) -> Self {
+        Self { consumable: Some(consumable), ..Self::new(name) }
+    }
+    pub fn apply_bonuses(&self, stats: &mut Stats) {
+        for (stat, bonus) in &self.bonuses {
+            stats[*stat] = stats[*stat].saturating_add(*bonus);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Item {
+    pub id: EntityID<Item>,
+    pub template: ItemTemplate,
+    /// None once the item has been used up
+    pub owner: Option<EntityID<Team>>
+}
+impl Item {
+    pub fn new(template: ItemTemplate, owner: Option<EntityID<Team>>) -> Self {
+        Self {
+            id: EntityID::roll(),
+            template,
+            owner
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.template.name
+    }
+}
+
+impl Sim<'_> {
+    /// Packs the first few consumables in the team's inventory to bring into the game
+    pub(crate) fn pack(&mut self, team: EntityID<Team>) {
+        let Some(team) = self.database.try_load(team) else { return };
+        let pack = team.inventory.into_iter()
+            .map(|item| self.database.load(item))
+            .filter(|item: &Item| item.template.consumable.is_some())
+            .take(PACK_SIZE)
+            .collect();
+        self.world.packs.insert(team.id, pack);
+    }
+
+    /// The consumable worth using this turn, and who to use it on, if any
+    pub(crate) fn choose_item(&self, team: EntityID<Team>, defender: ActiveCharacterID) -> Option<(EntityID<Item>, ActiveCharacterID)> {
+        let pack = self.world.packs.get(&team)?;
+        let allies: Vec<_> = self.world.rosters[&team].characters()
+            .map(|(_, character)| self.world.active_characters.get(character))
+            .collect();
+        pack.iter().find_map(|item| {
+            let target = match item.template.consumable? {
+                // Potions are saved for anyone about to go down
+                Consumable::Potion { .. } => allies.iter()
+                    .find(|ally| ally.progress_bars.get(&ProgressBarName::HP).is_some_and(|hp| hp.progress + 2 >= hp.max))
+                    .map(|ally| ally.id),
+                Consumable::Scroll => allies.iter().find(|ally| !ally.statuses.is_empty()).map(|ally| ally.id),
+                // Bombs are only worth it against a crowd
+                Consumable::Bomb { .. } => self.world.team_of(defender)
+                    .filter(|enemies| self.world.rosters[enemies].characters().count() >= 3)
+                    .map(|_| defender)
+            }?;
+            Some((item.id, target))
+        })
+    }
+}
+
+impl SavableEntity for Item {
+    const TABLE_NAME: &'static str = "items";
+}
@@ -1,8 +1,27 @@
 #![allow(dead_code)]
 pub mod entities;
 pub mod modifiers;
+pub mod abilities;
+pub mod loot;
+pub mod items;
 pub mod events;
+pub mod checks;
+pub mod statuses;
+pub mod hazards;
+pub mod bosses;
+pub mod summons;
 pub mod progress_bars;
 pub mod game;
+pub mod turn_log;
+pub mod replay;
+pub mod timelines;
+pub mod records;
+pub mod names;
+pub mod delve;
+pub mod settlement;
+pub mod dungeon;
 pub mod delver_display;
-pub mod database;
\ No newline at end of file
+pub mod database;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,47 @@
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::Combatant, events::Event, game::Sim, items::ItemTemplate};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LootEntry {
+    pub weight: u16,
+    /// None for a roll that drops nothing
+    pub item: Option<ItemTemplate>
+}
+
+/// Weighted drops. An empty table never drops anything.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LootTable(pub Vec<LootEntry>);
+impl LootTable {
+    pub fn add(&mut self, weight: u16, item: ItemTemplate) {
+        self.0.push(LootEntry { weight, item: Some(item) });
+    }
+    pub fn add_nothing(&mut self, weight: u16) {
+        self.0.push(LootEntry { weight, item: None });
+    }
+    pub fn roll(&self, rng: &mut ChaCha8Rng) -> Option<&ItemTemplate> {
+        self.0.choose_weighted(rng, |entry| entry.weight).ok()?.item.as_ref()
+    }
+}
+
+impl Sim<'_> {
+    /// Rolls the loot tables of every monster the winners downed. What drops is handed over when the game is settled.
+    pub(crate) fn roll_loot(&mut self) {
+        let Some(winner) = self.winner() else { return };
+        let beaten: Vec<_> = self.world.turn_order.iter()
+            .filter(|team| **team != winner)
+            .flat_map(|team| self.world.downed.get(team).into_iter().flatten())
+            .filter_map(|character| match self.world.active_characters.get(*character).character {
+                Combatant::Monster(monster) => Some(monster),
+                Combatant::Character(_) | Combatant::Summon(_) => None
+            })
+            .collect();
+        let events = beaten.into_iter()
+            .filter_map(|monster| self.database.load(monster).loot_table.roll(self.rng).cloned())
+            .map(|item| Event::DropLoot { team: winner, item })
+            .collect();
+        self.complete_events(events);
+    }
+}
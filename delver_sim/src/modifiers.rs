@@ -1,28 +1,73 @@
-use chronobase::EntityID;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{entities::Character, events::{Event, ExecutedEvent}, game::ActiveCharacter, progress_bars::{xp_bar, GameProgressBarLocation, ProgressBarName}};
+use crate::{abilities::Targeting, entities::Combatant, events::{CompletedEvent, Event, ExecutedEvent}, game::{ActiveCharacter, ActiveCharacterID, Game}, progress_bars::{xp_bar, GameProgressBarLocation, ProgressBarName}};
+
+/// How many times each character can react to others in a single turn. Stops two counterattackers trading blows forever.
+pub const REACTIONS_PER_TURN: u8 = 1;
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
 pub struct Modifier(pub ModifierID, pub ModifierType);
 impl Modifier {
-    pub fn new(character: EntityID<Character>, type_: ModifierType) -> Self {
-        Self(ModifierID::roll(character), type_)
+    pub fn new(owner: impl Into<Combatant>, type_: ModifierType) -> Self {
+        Self(ModifierID::roll(owner.into()), type_)
     }
 }
 
+/// A change to a character's modifiers that outlasts the game it happened in
 #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum ModifierChange {
+    Gained(Modifier),
+    Lost(ModifierType)
+}
+impl ModifierChange {
+    pub fn apply(&self, modifiers: &mut Vec<Modifier>) {
+        match self {
+            Self::Gained(modifier) => modifiers.push(*modifier),
+            Self::Lost(type_) => modifiers.retain(|modifier| modifier.1 != *type_)
+        }
+    }
+    /// Once their character has the change, moves it onto the active character's profile rather than tracking it themselves,
+    /// so it lasts if they're carried into another game
+    pub fn settle(&self, active_character: &mut ActiveCharacter) {
+        self.apply(&mut active_character.profile.modifiers);
+        match self {
+            Self::Gained(modifier) => active_character.gained.retain(|gained| gained.0 != modifier.0),
+            Self::Lost(type_) => active_character.lost.retain(|lost| lost != type_)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum ModifierType {
     Grinder,
-    Resilient
+    Resilient,
+    Restful,
+    /// Attacks back after being attacked, if still standing
+    Counterattack,
+    /// Strikes first when attacked, before the blow lands
+    Riposte,
+    /// Hurts anyone who damages them
+    Thorns,
+    /// Takes attacks aimed at badly hurt allies
+    Cover,
+    /// Steps in front of abilities aimed at allies
+    Intercept,
+    /// Takes an extra point of damage from every hit
+    Frail
+}
+impl ModifierType {
+    /// Reactions respond to what others do, and are limited to `REACTIONS_PER_TURN`
+    pub fn is_reaction(&self) -> bool {
+        matches!(self, Self::Counterattack | Self::Riposte | Self::Thorns | Self::Cover | Self::Intercept)
+    }
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
-pub struct ModifierID(pub EntityID<Character>, pub Uuid);
+pub struct ModifierID(pub Combatant, pub Uuid);
 impl ModifierID {
-    pub fn roll(character: EntityID<Character>) -> Self {
-        Self(character, Uuid::new_v4())
+    pub fn roll(owner: Combatant) -> Self {
+        Self(owner, Uuid::new_v4())
     }
 }
 
@@ -44,8 +89,9 @@ impl Modifier {
         }
         events
     }
-    pub fn pre_event(&self, character:&ActiveCharacter, event:&mut Event) -> Vec<Event> {
+    pub fn pre_event(&self, character:&ActiveCharacter, game: &Game, event:&mut Event) -> Vec<Event> {
         let mut events = Vec::new();
+        let standing = game.team_of(character.id).is_some();
         match self.1 {
             ModifierType::Resilient => if let Event::ProgressProgressBar { location: GameProgressBarLocation::Character(target, ProgressBarName::HP), amount } = event {
                 if *target == character.id && *amount > 1 {
@@ -53,20 +99,114 @@ impl Modifier {
                     events.push(Event::Say(format!("{target:?}'s resilience reduces the damage they take")));
                 }
             }
+            ModifierType::Frail => if let Event::ProgressProgressBar { location: GameProgressBarLocation::Character(target, ProgressBarName::HP), amount } = event {
+                if *target == character.id && *amount > 0 {
+                    *amount += 1;
+                }
+            }
+            ModifierType::Riposte => if let Event::Attack { attacker, target } = event {
+                if *target == character.id && standing {
+                    events.push(Event::ProgressProgressBar { location: GameProgressBarLocation::Character(*attacker, ProgressBarName::HP), amount: 1 });
+                }
+            }
+            ModifierType::Cover => if let Event::Attack { target, .. } = event {
+                if standing && *target != character.id && game.team_of(*target) == game.team_of(character.id) && badly_hurt(game, *target) {
+                    events.push(Event::Say(format!("{:?} covers {target:?}", character.id)));
+                    *target = character.id;
+                }
+            }
+            ModifierType::Intercept => if let Event::UseAbility { ability, target, .. } = event {
+                let aimed = matches!(ability.targeting, Targeting::Enemy);
+                if aimed && standing && *target != character.id && game.team_of(*target) == game.team_of(character.id) {
+                    events.push(Event::Say(format!("{:?} intercepts {}", character.id, ability.name)));
+                    *target = character.id;
+                }
+            }
             _ => ()
         }
         events
     }
-    pub fn post_event(&self, character:&ActiveCharacter, event:&ExecutedEvent) -> Vec<Event> {
+    pub fn post_event(&self, character:&ActiveCharacter, game: &Game, event:&ExecutedEvent, outcomes: &[CompletedEvent]) -> Vec<Event> {
         let mut events = Vec::new();
+        let standing = game.team_of(character.id).is_some();
         match self.1 {
             ModifierType::Grinder => if let Event::Attack {target, attacker } = event.0 {
                 if target == character.id || attacker == character.id {
                     events.push(Event::ProgressProgressBar { location: GameProgressBarLocation::Character(character.id, ProgressBarName::XP), amount: 1 })
                 }
             }
+            ModifierType::Counterattack => if let Event::Attack { attacker, target } = event.0 {
+                if target == character.id && standing && game.team_of(attacker).is_some() {
+                    events.push(Event::Attack { attacker: character.id, target: attacker });
+                }
+            }
+            ModifierType::Thorns => if let Some(source) = event.0.source() {
+                let damaged = outcomes.iter().any(|outcome| outcome.damage_to(character.id) > 0);
+                if source != character.id && damaged && game.team_of(source).is_some() {
+                    events.push(Event::ProgressProgressBar { location: GameProgressBarLocation::Character(source, ProgressBarName::HP), amount: 1 });
+                }
+            }
             _ => ()
         }
         events
     }
-}
\ No newline at end of file
+    pub fn on_rest(&self, character:&ActiveCharacter) -> Vec<Event> {
+        let mut events = Vec::new();
+        if let ModifierType::Restful = self.1 {
+            events.push(Event::Say(format!("{:?} sleeps soundly", character.id)));
+            events.push(Event::RegressProgressBar { location: GameProgressBarLocation::Character(character.id, ProgressBarName::HP), amount: 1 })
+        }
+        events
+    }
+}
+/// One more hit would down them
+fn badly_hurt(game: &Game, character: ActiveCharacterID) -> bool {
+    let hp = game.get_progress_bar(GameProgressBarLocation::Character(character, ProgressBarName::HP));
+    hp.progress + 2 >= hp.max
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{game::{Position, Sim}, testing::{database, team}};
+
+    /// Every attack in the event and everything it led to
+    fn attacks(event: &CompletedEvent) -> Vec<(ActiveCharacterID, ActiveCharacterID)> {
+        let own = match event.event.0 {
+            Event::Attack { attacker, target } => Some((attacker, target)),
+            _ => None
+        };
+        event.pre_responses.iter().chain(&event.outcomes).chain(&event.post_responses)
+            .fold(own.into_iter().collect(), |mut all, event| {
+                all.extend(attacks(event));
+                all
+            })
+    }
+
+    #[test]
+    fn counterattacks_stop_once_both_sides_have_reacted() {
+        let database = database("counterattack");
+        let (crabs, pirates) = (team(&database, "Crab"), team(&database, "Pirate"));
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut sim = Sim::new(database, &mut rng, crabs, pirates, None);
+        sim.log_events = false;
+
+        let crab = sim.world.rosters[&crabs].get(Position::Top).unwrap();
+        let pirate = sim.world.rosters[&pirates].get(Position::Top).unwrap();
+        sim.add_modifier(crab, ModifierType::Counterattack, false);
+        sim.add_modifier(pirate, ModifierType::Counterattack, false);
+        // Tough enough that nobody goes down, so only running out of reactions ends it
+        for character in [crab, pirate] {
+            sim.world.get_progress_bar_mut(GameProgressBarLocation::Character(character, ProgressBarName::HP)).max = 100;
+        }
+
+        let exchange = Event::Attack { attacker: crab, target: pirate }.complete(&mut sim);
+        assert_eq!(attacks(&exchange), [(crab, pirate), (pirate, crab), (crab, pirate)]);
+        for character in [crab, pirate] {
+            assert_eq!(sim.world.reactions[&character], REACTIONS_PER_TURN);
+        }
+    }
+}
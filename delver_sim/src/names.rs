@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use chronobase::{EntityID, SavableEntity};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{database::{DatabaseManager, SaveError}, entities::{Character, Team}};
+
+/// Searches allow one typo for every this many characters searched for
+const CHARACTERS_PER_TYPO: usize = 3;
+
+/// Every team and character name, folded so lookups ignore case and spacing.
+/// Kept up to date as teams and characters are saved, so anything saved before it existed is picked up the next time it's saved.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NameIndex {
+    pub teams: BTreeMap<String, Vec<EntityID<Team>>>,
+    pub characters: BTreeMap<String, Vec<EntityID<Character>>>
+}
+impl NameIndex {
+    /// There's only the one index
+    pub fn id() -> EntityID<NameIndex> {
+        EntityID::from(Uuid::nil())
+    }
+}
+impl SavableEntity for NameIndex {
+    const TABLE_NAME: &'static str = "name_index";
+}
+
+/// How names are compared: lowercase, with any run of whitespace as a single space
+pub fn fold(name: &str) -> String {
+    name.split_whitespace().join(" ").to_lowercase()
+}
+
+/// Files the id under its name, dropping it from any name it had before. False if it was already there.
+fn register<T>(names: &mut BTreeMap<String, Vec<EntityID<T>>>, id: EntityID<T>, name: &str) -> bool {
+    let name = fold(name);
+    if names.get(&name).is_some_and(|ids| ids.contains(&id)) {
+        return false
+    }
+    names.retain(|_, ids| {
+        ids.retain(|other| *other != id);
+        !ids.is_empty()
+    });
+    names.entry(name).or_default().push(id);
+    true
+}
+
+/// Closest first: exact matches, then names starting with the search, then names containing it, then names a few typos away
+fn search<T>(names: &BTreeMap<String, Vec<EntityID<T>>>, query: &str) -> Vec<EntityID<T>> {
+    let query = fold(query);
+    names.iter()
+        .filter_map(|(name, ids)| closeness(name, &query).map(|closeness| (closeness, ids)))
+        .sorted_by_key(|(closeness, _)| *closeness)
+        .flat_map(|(_, ids)| ids.iter().copied())
+        .collect()
+}
+fn closeness(name: &str, query: &str) -> Option<usize> {
+    if name == query {
+        Some(0)
+    } else if name.starts_with(query) {
+        Some(1)
+    } else if name.contains(query) {
+        Some(2)
+    } else {
+        let typos = edit_distance(name, query);
+        (typos <= query.chars().count() / CHARACTERS_PER_TYPO).then_some(2 + typos)
+    }
+}
+/// The fewest single character insertions, deletions and substitutions to turn one into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Entities that can be looked up by name. Everything else leaves the index alone.
+pub(crate) trait Named {
+    /// Called before the entity is saved, to turn it down if its name can't be used
+    fn check_name(&self, _database: &DatabaseManager) -> Result<(), SaveError> {
+        Ok(())
+    }
+    /// Called once the entity has been saved, to add it to the index
+    fn index_name(&self, _database: &DatabaseManager) -> Result<(), SaveError> {
+        Ok(())
+    }
+}
+impl Named for Team {
+    fn check_name(&self, database: &DatabaseManager) -> Result<(), SaveError> {
+        if !database.unique_team_names() {
+            return Ok(())
+        }
+        // Only a new name can be turned down, so teams that already share one can still be saved
+        let saved: Option<Team> = database.try_load(self.id);
+        if saved.is_some_and(|saved| fold(&saved.name) == fold(&self.name)) {
+            return Ok(())
+        }
+        if database.teams_named(&self.name).iter().any(|id| *id != self.id) {
+            return Err(SaveError::NameTaken(self.name.clone()))
+        }
+        Ok(())
+    }
+    fn index_name(&self, database: &DatabaseManager) -> Result<(), SaveError> {
+        let mut names = database.names();
+        if register(&mut names.teams, self.id, &self.name) {
+            database.try_save(names)?;
+        }
+        Ok(())
+    }
+}
+impl Named for Character {
+    fn index_name(&self, database: &DatabaseManager) -> Result<(), SaveError> {
+        let mut names = database.names();
+        if register(&mut names.characters, self.id, &self.name) {
+            database.try_save(names)?;
+        }
+        Ok(())
+    }
+}
+
+impl DatabaseManager {
+    pub fn names(&self) -> NameIndex {
+        self.try_load(NameIndex::id()).unwrap_or_default()
+    }
+    /// Teams with exactly this name, give or take case and spacing
+    pub fn teams_named(&self, name: &str) -> Vec<EntityID<Team>> {
+        self.names().teams.get(&fold(name)).cloned().unwrap_or_default()
+    }
+    pub fn characters_named(&self, name: &str) -> Vec<EntityID<Character>> {
+        self.names().characters.get(&fold(name)).cloned().unwrap_or_default()
+    }
+    /// Teams whose names are close to the search, closest first
+    pub fn search_teams(&self, query: &str) -> Vec<EntityID<Team>> {
+        search(&self.names().teams, query)
+    }
+    pub fn search_characters(&self, query: &str) -> Vec<EntityID<Character>> {
+        search(&self.names().characters, query)
+    }
+    /// Builds the index again from scratch, for names saved before it existed or changed behind its back.
+    /// The database can't go through a whole table, so this covers the given teams, every team and character that's played a game,
+    /// and everyone on those teams' rosters.
+    pub fn reindex_names(&self, teams: impl IntoIterator<Item = EntityID<Team>>) -> Result<NameIndex, SaveError> {
+        let games = self.games();
+        let teams: Vec<Team> = teams.into_iter()
+            .chain(games.iter().flat_map(|listing| listing.record.teams.iter().copied()))
+            .unique()
+            .filter_map(|team| self.try_load(team))
+            .collect();
+        let characters: Vec<Character> = teams.iter().flat_map(|team| team.roster.iter().copied())
+            .chain(games.iter().flat_map(|listing| listing.record.characters.iter().copied()))
+            .unique()
+            .filter_map(|character| self.try_load(character))
+            .collect();
+
+        let mut names = NameIndex::default();
+        for team in &teams {
+            register(&mut names.teams, team.id, &team.name);
+        }
+        for character in &characters {
+            register(&mut names.characters, character.id, &character.name);
+        }
+        self.try_save(names.clone())?;
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{progress_bars::Colour, testing::{database, reopen}};
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("crabs", "crabs"), 0);
+        assert_eq!(edit_distance("crabs", "crab"), 1);
+        assert_eq!(edit_distance("crabs", "grabs"), 1);
+        assert_eq!(edit_distance("crabs", "carbs"), 2);
+        assert_eq!(edit_distance("", "crabs"), 5);
+    }
+
+    #[test]
+    fn names_are_folded() {
+        assert_eq!(fold("  Baltimore \t CRABS "), "baltimore crabs");
+    }
+
+    #[test]
+    fn renaming_moves_the_id() {
+        let mut names = BTreeMap::new();
+        let id: EntityID<Team> = EntityID::roll();
+        assert!(register(&mut names, id, "Baltimore Crabs"));
+        assert!(!register(&mut names, id, "baltimore  crabs"));
+        assert!(register(&mut names, id, "Antalya Pirates"));
+        assert_eq!(names.len(), 1);
+        assert_eq!(names["antalya pirates"], vec![id]);
+    }
+
+    #[test]
+    fn closest_matches_come_first() {
+        let mut names = BTreeMap::new();
+        let [exact, prefix, contains, typo]: [EntityID<Team>; 4] = std::array::from_fn(|_| EntityID::roll());
+        register(&mut names, exact, "Crabs");
+        register(&mut names, prefix, "Crabs United");
+        register(&mut names, contains, "Baltimore Crabs");
+        register(&mut names, typo, "Grabs");
+        register(&mut names, EntityID::roll(), "Antalya Pirates");
+        assert_eq!(search(&names, "crabs"), vec![exact, prefix, contains, typo]);
+        assert!(search(&names, "xyz").is_empty());
+    }
+
+    #[test]
+    fn only_new_team_names_have_to_be_unique() {
+        let database = database("unique-names");
+        let first = Team::new("Crabs".to_string(), Colour::Red);
+        let second = Team::new("Crabs".to_string(), Colour::Cyan);
+        database.save(first.clone());
+        database.save(second.clone());
+
+        let strict = reopen("unique-names").with_unique_team_names();
+        assert!(strict.try_save(first).is_ok());
+        assert!(matches!(strict.try_save(Team::new("  crabs".to_string(), Colour::Red)), Err(SaveError::NameTaken(_))));
+        let renamed = Team { name: "Baltimore Crabs".to_string(), ..second };
+        assert!(strict.try_save(renamed.clone()).is_ok());
+        assert!(matches!(strict.try_save(Team { name: "Crabs".to_string(), ..renamed }), Err(SaveError::NameTaken(_))));
+    }
+}
@@ -8,12 +8,17 @@ use crate::game::ActiveCharacterID;
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProgressBarName {
     XP,
-    HP
+    HP,
+    Depth,
+    BossHP,
+    Supplies,
+    Energy
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameProgressBarLocation {
     Character(ActiveCharacterID, ProgressBarName),
+    Game(ProgressBarName)
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -28,6 +33,12 @@ impl ProgressBarStyle {
             Self::Drain => "decrease",
         }
     }
+    pub fn reverse_verb(&self) -> &'static str {
+        match self {
+            Self::Fill => "decrease",
+            Self::Drain => "increase",
+        }
+    }
 }
 
 /// Colours that are printable to discord
@@ -57,7 +68,7 @@ impl From<Colour> for colored::Color {
     }
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct ProgressBar {
     pub max: u16,
     pub name: ProgressBarName,
@@ -72,6 +83,9 @@ impl ProgressBar {
     pub fn increment(&mut self, delta:u16) {
         self.progress = min(self.max, self.progress + delta);
     }
+    pub fn decrement(&mut self, delta:u16) {
+        self.progress = self.progress.saturating_sub(delta);
+    }
     pub fn complete(&self) -> bool {
         self.max <= self.progress
     }
@@ -111,4 +125,19 @@ pub fn health_bar() -> ProgressBar {
 }
 pub fn xp_bar() -> ProgressBar {
     ProgressBar::new(4, ProgressBarName::XP, Colour::Blue, ProgressBarStyle::Fill)
+}
+/// Fills as a delve reaches each new floor
+pub fn depth_bar(floors: u16) -> ProgressBar {
+    ProgressBar::new(floors, ProgressBarName::Depth, Colour::Yellow, ProgressBarStyle::Fill)
+}
+/// Used up as a delve makes camp
+pub fn supplies_bar(supplies: u16) -> ProgressBar {
+    ProgressBar::new(supplies, ProgressBarName::Supplies, Colour::Green, ProgressBarStyle::Drain)
+}
+/// Filled by using abilities, and drained a little each turn
+pub fn energy_bar() -> ProgressBar {
+    ProgressBar::new(3, ProgressBarName::Energy, Colour::Cyan, ProgressBarStyle::Drain)
+}
+pub fn boss_health_bar(hp: u16) -> ProgressBar {
+    ProgressBar::new(hp, ProgressBarName::BossHP, Colour::Pink, ProgressBarStyle::Drain)
 }
\ No newline at end of file
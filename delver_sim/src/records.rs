@@ -0,0 +1,209 @@
+use std::time::SystemTime;
+
+use chronobase::{EntityID, SavableEntity};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{database::DatabaseManager, delve::Delve, entities::{Character, Combatant, Team}, game::{Game, GameOutcome, Sim}};
+
+/// What kind of game it was
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Teams set against each other
+    #[default]
+    Arena,
+    /// One of the encounters on a delve
+    Delve(EntityID<Delve>)
+}
+impl GameMode {
+    pub fn delve(self) -> Option<EntityID<Delve>> {
+        match self {
+            GameMode::Arena => None,
+            GameMode::Delve(delve) => Some(delve)
+        }
+    }
+}
+
+/// Where in the league's calendar a game was played
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GameDay {
+    pub season: u32,
+    pub day: u32
+}
+
+/// Who played a game, when, and how it went, so games can be listed without going through everything that happened in them
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub mode: GameMode,
+    /// None for games played outside the calendar
+    pub day: Option<GameDay>,
+    /// The saved teams that took part. Monster rosters never are.
+    pub teams: Vec<EntityID<Team>>,
+    pub characters: Vec<EntityID<Character>>,
+    pub started: Option<SystemTime>,
+    pub ended: Option<SystemTime>,
+    /// How many turns were played, once it's over
+    pub turns: u32,
+    pub outcome: Option<GameOutcome>
+}
+
+impl GameRecord {
+    /// The record of a game being set up now
+    pub fn starting(mode: GameMode, day: Option<GameDay>) -> Self {
+        Self { mode, day, started: Some(SystemTime::now()), ..Self::default() }
+    }
+}
+
+impl Sim<'_> {
+    pub(crate) fn start_record(&mut self) {
+        self.note_participants();
+    }
+    pub(crate) fn finish_record(&mut self) {
+        self.note_participants();
+        let (turns, outcome) = (self.world.turn, self.world.outcome());
+        let record = &mut self.world.record;
+        record.ended = Some(SystemTime::now());
+        record.turns = turns;
+        record.outcome = outcome;
+    }
+    /// Everyone who's been in the game so far
+    fn note_participants(&mut self) {
+        let teams = self.world.turn_order.iter().copied()
+            .filter(|team| self.database.try_load(*team).is_some())
+            .collect();
+        let characters = self.world.active_characters.0.values()
+            .filter_map(|active_character| match active_character.character {
+                Combatant::Character(character) => Some(character),
+                _ => None
+            })
+            .collect();
+        (self.world.record.teams, self.world.record.characters) = (teams, characters);
+    }
+}
+
+/// One game in a listing of settled games. Every settled game saves a version of the listing of all games,
+/// and of the listing of every saved team and character that took part, so a listing's history is its games in the order they finished
+/// and its latest version is the most recent one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameListing {
+    /// Which listing this is a version of. Listings saved before there were more than one are all in the listing of every game.
+    #[serde(default = "GameListing::id")]
+    pub id: EntityID<GameListing>,
+    pub game: EntityID<Game>,
+    pub record: GameRecord
+}
+impl GameListing {
+    /// The listing of every game
+    pub fn id() -> EntityID<GameListing> {
+        EntityID::from(Uuid::nil())
+    }
+    /// The listing of the games a team played in
+    pub fn team(team: EntityID<Team>) -> EntityID<GameListing> {
+        listing_for(team)
+    }
+    /// The listing of the games a character played in
+    pub fn character(character: EntityID<Character>) -> EntityID<GameListing> {
+        listing_for(character)
+    }
+    pub fn of(game: &Game) -> Self {
+        Self { id: Self::id(), game: game.game_id, record: game.record.clone() }
+    }
+    /// The game's entry in every listing it belongs in
+    pub fn all_of(game: &Game) -> Vec<Self> {
+        let listing = Self::of(game);
+        let teams = game.record.teams.iter().map(|team| Self::team(*team));
+        let characters = game.record.characters.iter().map(|character| Self::character(*character));
+        let keyed: Vec<_> = teams.chain(characters).map(|id| Self { id, ..listing.clone() }).collect();
+        std::iter::once(listing).chain(keyed).collect()
+    }
+}
+impl SavableEntity for GameListing {
+    const TABLE_NAME: &'static str = "game_listing";
+}
+
+/// Listings share a table, each under the id of whatever it lists the games of.
+/// Ids serialise the same whatever they're the id of, so it's carried over as is.
+fn listing_for<T>(id: EntityID<T>) -> EntityID<GameListing> where EntityID<T>: Serialize {
+    let id = serde_json::to_value(id).expect("Entity ids should always serialise");
+    serde_json::from_value(id).expect("Entity ids should deserialise from any other entity's id")
+}
+
+impl DatabaseManager {
+    /// Every settled game, in the order they finished
+    pub fn games(&self) -> Vec<GameListing> {
+        self.history(GameListing::id())
+    }
+    /// The last n games settled, most recent first
+    pub fn recent_games(&self, n: usize) -> Vec<GameListing> {
+        self.database.load(GameListing::id(), None, n).unwrap_or_default()
+    }
+    /// The game settled most recently
+    pub fn latest_game(&self) -> Option<GameListing> {
+        self.try_load(GameListing::id())
+    }
+    /// Every game the team played in, in the order they finished
+    pub fn team_games(&self, team: EntityID<Team>) -> Vec<GameListing> {
+        self.history(GameListing::team(team))
+    }
+    /// Every game the character played in, in the order they finished
+    pub fn character_games(&self, character: EntityID<Character>) -> Vec<GameListing> {
+        self.history(GameListing::character(character))
+    }
+    /// Games that started in the given range, end included, in the order they finished.
+    /// Goes back through the most recent games only as far as games that ended before the range.
+    pub fn games_between(&self, from: SystemTime, to: SystemTime) -> Vec<GameListing> {
+        let mut n = 16;
+        let recent = loop {
+            let recent = self.recent_games(n);
+            // Anything that finished before the range started before it too, and so did every game listed before it
+            let reached = recent.last().is_some_and(|listing| listing.record.ended.is_some_and(|ended| ended < from));
+            if reached || recent.len() < n {
+                break recent
+            }
+            n *= 2;
+        };
+        recent.into_iter().rev().filter(|listing| listing.record.started.is_some_and(|started| from <= started && started <= to)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::testing::{database, team};
+
+    const GAMES: usize = 20;
+
+    #[test]
+    fn listings_by_team_and_by_time() {
+        let mut database = database("listings");
+        let (crabs, pirates, ghosts) = (team(&database, "Crab"), team(&database, "Pirate"), team(&database, "Ghost"));
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        // The crabs play every game, against the pirates and the ghosts in turn
+        let mut games = Vec::new();
+        let mut marks = Vec::new();
+        for game in 0..GAMES {
+            marks.push(SystemTime::now());
+            let opponent = if game % 2 == 0 { pirates } else { ghosts };
+            let mut sim = Sim::new(database, &mut rng, crabs, opponent, None);
+            sim.log_events = false;
+            sim.turn();
+            sim.settle().unwrap();
+            games.push(sim.world.game_id);
+            database = sim.database;
+        }
+        let listed = |listings: Vec<GameListing>| listings.into_iter().map(|listing| listing.game).collect::<Vec<_>>();
+
+        assert_eq!(listed(database.team_games(crabs)), games);
+        assert_eq!(listed(database.team_games(pirates)), games.iter().copied().step_by(2).collect::<Vec<_>>());
+        let ghost: Team = database.load(ghosts);
+        assert_eq!(listed(database.character_games(ghost.roster[0])), games.iter().copied().skip(1).step_by(2).collect::<Vec<_>>());
+
+        // Further back than the first batch of recent games
+        assert_eq!(listed(database.games_between(marks[2], marks[GAMES - 1])), games[2..GAMES - 1]);
+        assert_eq!(listed(database.games_between(marks[0], SystemTime::now())), games);
+    }
+}
@@ -0,0 +1,228 @@
+use std::mem;
+
+use chronobase::EntityID;
+use itertools::Itertools;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde_json::Value;
+
+use crate::{database::DatabaseManager, events::{CompletedEvent, Event}, game::{Game, Sim, RULES_VERSION}, turn_log::TurnLog};
+
+/// Left out of the state hash. Which game it is, where it came from, who played it when and whether it's been settled don't change how it plays,
+/// and events and reactions only last the turn.
+const UNHASHED: [&str; 8] = ["game_id", "log", "forked_from", "record", "settled", "latest_events", "turn_events", "reactions"];
+
+/// FNV-1a. Unlike the standard library's hashers it's pinned down, so hashes can be compared between builds.
+struct StableHasher(u64);
+impl StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+    /// Maps are hashed with their keys in order, so the order a `HashMap` happens to iterate in doesn't matter
+    fn write_value(&mut self, value: &Value) {
+        match value {
+            Value::Object(map) => {
+                self.write(b"{");
+                for (key, value) in map.iter().sorted_by_key(|(key, _)| *key) {
+                    self.write(format!("{key:?}:").as_bytes());
+                    self.write_value(value);
+                }
+                self.write(b"}");
+            }
+            Value::Array(values) => {
+                self.write(b"[");
+                for value in values {
+                    self.write_value(value);
+                    self.write(b",");
+                }
+                self.write(b"]");
+            }
+            value => self.write(value.to_string().as_bytes())
+        }
+    }
+}
+
+impl Game {
+    /// A hash of everything about the game that decides how it plays out from here
+    pub fn state_hash(&self) -> u64 {
+        let mut state = serde_json::to_value(self).expect("Games should always serialise");
+        if let Value::Object(fields) = &mut state {
+            fields.retain(|field, _| !UNHASHED.contains(&field.as_str()));
+        }
+        let mut hasher = StableHasher(0xcbf29ce484222325);
+        hasher.write_value(&state);
+        hasher.0
+    }
+}
+
+/// Where two plays of a game first stopped matching
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    pub turn: u32,
+    /// The first event that came out differently, as each play had it. None for a play that had run out of events by then,
+    /// and for both if every event matched but the game still came out differently.
+    pub expected: Option<Event>,
+    pub actual: Option<Event>
+}
+impl Divergence {
+    /// Compares two logs turn by turn
+    pub fn between(expected: &[TurnLog], actual: &[TurnLog]) -> Option<Divergence> {
+        let mismatch = expected.iter().zip_longest(actual)
+            .find(|turns| turns.as_ref().both().is_none_or(|(expected, actual)| {
+                expected.turn != actual.turn || expected.state_hash != actual.state_hash || expected.between != actual.between || expected.events != actual.events
+            }))?;
+        let turn = mismatch.as_ref().reduce(|expected, _| expected).turn;
+        let (expected, actual) = mismatch.map_any(executed_in, executed_in).or_default();
+        let (expected, actual) = expected.into_iter().zip_longest(actual)
+            .find(|events| events.as_ref().both().is_none_or(|(expected, actual)| expected != actual))
+            .map_or((None, None), |events| events.map_any(Some, Some).or_default());
+        Some(Divergence { turn, expected: expected.cloned(), actual: actual.cloned() })
+    }
+    /// Compares the logs of two saved games, such as two forks of the same game
+    pub fn between_games(database: &DatabaseManager, expected: EntityID<Game>, actual: EntityID<Game>) -> Option<Divergence> {
+        let log = |game| database.history(database.load::<_, Game>(game).log);
+        Divergence::between(&log(expected), &log(actual))
+    }
+}
+
+/// Every event in the logged turn, including those done before it, in the order they were carried out
+fn executed_in(log: &TurnLog) -> Vec<&Event> {
+    executed(&log.between).into_iter().chain(executed(&log.events)).collect()
+}
+/// Every event in the list, in the order they were carried out
+fn executed(events: &[CompletedEvent]) -> Vec<&Event> {
+    events.iter().flat_map(|event| {
+        executed(&event.pre_responses).into_iter()
+            .chain([&event.event.0])
+            .chain(executed(&event.outcomes))
+            .chain(executed(&event.post_responses))
+    }).collect()
+}
+
+impl Sim<'_> {
+    /// Plays the saved game again from its start, with the seed it was played with, and finds the first turn that came out differently to the first time.
+    /// None if every turn came out the same. The sim is left as it was.
+    pub fn divergence(&mut self, game: EntityID<Game>) -> Option<Divergence> {
+        let start: Game = self.database.history(game).into_iter().next().expect("The game should have been saved when it started");
+        assert_eq!(start.rules_version, RULES_VERSION, "The game was played under different rules");
+        let logged: Vec<TurnLog> = self.database.history(start.log);
+
+        let mut rng = ChaCha8Rng::from_seed(start.seed);
+        rng.set_word_pos(start.stream_position);
+        let delve = start.record.mode.delve().and_then(|delve| self.database.try_load(delve));
+
+        let rng = mem::replace(self.rng, rng);
+        let world = mem::replace(&mut self.world, start);
+        let delve = mem::replace(&mut self.delve, delve);
+        let (log_events, save_turns) = (self.log_events, self.save_turns);
+        (self.log_events, self.save_turns) = (false, false);
+
+        // A replay that finishes early comes up short, which shows as the first turn it didn't get to
+        let mut replayed = Vec::new();
+        while replayed.len() < logged.len() && !self.finished() {
+            // Whatever was done between turns isn't up to the game, so it's done again as it was
+            let between = logged[replayed.len()].between.clone();
+            for event in &between {
+                event.replay(&mut self.world, &self.database);
+            }
+            self.turn();
+            replayed.push(TurnLog::of(&self.world, between));
+        }
+
+        *self.rng = rng;
+        (self.world, self.delve) = (world, delve);
+        (self.log_events, self.save_turns) = (log_events, save_turns);
+        Divergence::between(&logged, &replayed)
+    }
+    /// Plays the saved game again, panicking if any turn comes out differently to the first time
+    pub fn replay(&mut self, game: EntityID<Game>) {
+        if let Some(divergence) = self.divergence(game) {
+            panic!("The game played out differently on turn {}: {divergence:?}", divergence.turn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entities::Team, events::ExecutedEvent, testing::{database, team}};
+
+    fn log(turn: u32, said: &[&str], state_hash: u64) -> TurnLog {
+        let events = said.iter().map(|said| CompletedEvent {
+            event: ExecutedEvent(Event::Say(said.to_string())),
+            pre_responses: Vec::new(),
+            outcomes: Vec::new(),
+            post_responses: Vec::new()
+        }).collect();
+        TurnLog { id: EntityID::roll(), game: EntityID::roll(), turn, between: Vec::new(), events, state_hash }
+    }
+
+    #[test]
+    fn matching_logs_have_no_divergence() {
+        let logs = [log(1, &["a"], 1), log(2, &["b", "c"], 2)];
+        assert!(Divergence::between(&logs, &logs.clone()).is_none());
+    }
+
+    #[test]
+    fn first_differing_event() {
+        let expected = [log(1, &["a"], 1), log(2, &["b", "c"], 2)];
+        let actual = [log(1, &["a"], 1), log(2, &["b", "d"], 3)];
+        let divergence = Divergence::between(&expected, &actual).unwrap();
+        assert_eq!(divergence.turn, 2);
+        assert_eq!(divergence.expected, Some(Event::Say("c".to_string())));
+        assert_eq!(divergence.actual, Some(Event::Say("d".to_string())));
+    }
+
+    #[test]
+    fn running_out_of_turns() {
+        let expected = [log(1, &["a"], 1), log(2, &["b"], 2)];
+        let divergence = Divergence::between(&expected, &expected[..1]).unwrap();
+        assert_eq!(divergence.turn, 2);
+        assert_eq!(divergence.expected, Some(Event::Say("b".to_string())));
+        assert_eq!(divergence.actual, None);
+    }
+
+    #[test]
+    fn same_events_different_state() {
+        let divergence = Divergence::between(&[log(1, &["a"], 1)], &[log(1, &["a"], 2)]).unwrap();
+        assert_eq!(divergence.turn, 1);
+        assert_eq!((divergence.expected, divergence.actual), (None, None));
+    }
+
+    /// Plays a game between the teams from the seed until it's over, or long enough to tell
+    fn play(database: DatabaseManager, seed: u64, crabs: EntityID<Team>, pirates: EntityID<Team>) -> (DatabaseManager, EntityID<Game>) {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut sim = Sim::new(database, &mut rng, crabs, pirates, None);
+        sim.log_events = false;
+        while !sim.finished() && sim.world.turn < 200 {
+            sim.turn();
+        }
+        (sim.database, sim.world.game_id)
+    }
+
+    #[test]
+    fn a_seeded_game_plays_the_same_again() {
+        let database = database("replay");
+        let (crabs, pirates) = (team(&database, "Crab"), team(&database, "Pirate"));
+        let (database, game) = play(database, 7, crabs, pirates);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut sim = Sim::new(database, &mut rng, crabs, pirates, None);
+        assert!(sim.divergence(game).is_none());
+    }
+
+    #[test]
+    fn two_games_from_the_same_seed_match() {
+        let database = database("same-seed");
+        let (crabs, pirates) = (team(&database, "Crab"), team(&database, "Pirate"));
+        let (database, first) = play(database, 7, crabs, pirates);
+        let (database, second) = play(database, 7, crabs, pirates);
+        assert!(Divergence::between_games(&database, first, second).is_none());
+
+        let (database, other) = play(database, 8, crabs, pirates);
+        assert!(Divergence::between_games(&database, first, other).is_some());
+    }
+}
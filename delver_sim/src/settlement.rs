@@ -0,0 +1,153 @@
+use chronobase::EntityID;
+use rand::seq::SliceRandom;
+
+use crate::{database::SaveError, entities::{Character, Combatant, Stats, Team}, game::{GameOutcome, Sim}, items::Item, progress_bars::{GameProgressBarLocation, ProgressBarName}, records::GameListing};
+
+/// Experience for taking part in a fight, on top of anything on their XP bar
+const XP_PER_FIGHT: u16 = 1;
+
+impl Sim<'_> {
+    /// Writes the consequences of the game back to the teams and characters that took part, each as one new version tagged with the game.
+    /// Loot is handed out, used up consumables leave inventories, characters gain XP, injuries and permanent modifier changes,
+    /// and careers are counted. Monsters, summons and unsaved teams are left alone. The finished game is saved and listed too.
+    /// Settling a game that's already been settled does nothing.
+    pub fn settle(&mut self) -> Result<(), SaveError> {
+        if self.world.settled {
+            return Ok(())
+        }
+        self.roll_loot();
+        let teams: Vec<_> = self.world.turn_order.iter().copied().collect();
+        for team in teams {
+            self.settle_team(team)?;
+        }
+        self.world.loot.clear();
+        self.world.used.clear();
+        self.world.packs.clear();
+        self.world.modifier_changes.clear();
+        self.finish_record();
+        self.world.settled = true;
+        self.database.try_save(self.world.clone())?;
+        for listing in GameListing::all_of(&self.world) {
+            self.database.try_save(listing)?;
+        }
+        Ok(())
+    }
+
+    fn settle_team(&mut self, id: EntityID<Team>) -> Result<(), SaveError> {
+        let Some(mut team): Option<Team> = self.database.try_load(id) else { return Ok(()) };
+        let game = self.world.game_id;
+        // Traps, treasure and resting happen in games of their own, and don't count towards careers
+        let fight = self.world.turn_order.len() > 1;
+        let outcome = self.world.outcome();
+
+        let found: Vec<_> = self.world.loot.iter().filter(|(team, _)| *team == id).map(|(_, template)| template.clone()).collect();
+        for template in found {
+            let item = Item::new(template, Some(id));
+            team.inventory.push(item.id);
+            self.database.try_save(item)?;
+        }
+
+        let used = self.world.used.remove(&id).unwrap_or_default();
+        team.inventory.retain(|item| !used.contains(item));
+        for item in used {
+            let mut item: Item = self.database.load(item);
+            item.owner = None;
+            self.database.try_save(item)?;
+        }
+
+        let standing: Vec<_> = self.world.rosters.get(&id).into_iter().flat_map(|roster| roster.characters().map(|(_, character)| character)).collect();
+        let escaped = self.world.escaped.get(&id).cloned().unwrap_or_default();
+        let downed = self.world.downed.get(&id).cloned().unwrap_or_default();
+
+        if fight {
+            team.career.games += 1;
+            match outcome {
+                Some(GameOutcome::Victory(winner)) if winner == id => team.career.victories += 1,
+                Some(GameOutcome::Escape(fled)) if fled == id => team.career.escapes += 1,
+                _ => ()
+            }
+        }
+        // Summons going down isn't held against the team
+        team.career.downs += downed.iter().filter(|downed| matches!(self.world.active_characters.get(**downed).character, Combatant::Character(_))).count() as u32;
+        team.last_game = Some(game);
+        self.database.try_save(team)?;
+
+        for active_id in standing.into_iter().chain(escaped.iter().copied()).chain(downed.iter().copied()) {
+            let Combatant::Character(character) = self.world.active_characters.get(active_id).character else { continue };
+            let Some(mut saved): Option<Character> = self.database.try_load(character) else { continue };
+
+            // Their XP bar is emptied into their career, so it isn't counted twice if they stay active
+            let xp_bar = GameProgressBarLocation::Character(active_id, ProgressBarName::XP);
+            let xp = self.world.active_characters.get(active_id).progress_bars.get(&ProgressBarName::XP).map(|bar| bar.progress).unwrap_or_default();
+            if xp > 0 {
+                self.world.get_progress_bar_mut(xp_bar).decrement(xp);
+            }
+            saved.gain_xp(xp + if fight { XP_PER_FIGHT } else { 0 });
+
+            if downed.contains(&active_id) {
+                saved.injuries.push(*Stats::canonical_order().choose(self.rng).unwrap());
+                saved.career.downs += 1;
+            }
+            if fight {
+                saved.career.games += 1;
+                if outcome == Some(GameOutcome::Victory(id)) {
+                    saved.career.victories += 1;
+                }
+                if escaped.contains(&active_id) {
+                    saved.career.escapes += 1;
+                }
+            }
+
+            let active_character = self.world.active_characters.get_mut(active_id);
+            for (_, change) in self.world.modifier_changes.iter().filter(|(changed, _)| *changed == character) {
+                change.apply(&mut saved.modifiers);
+                change.settle(active_character);
+            }
+
+            saved.last_game = Some(game);
+            self.database.try_save(saved)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use serde_json::Value;
+
+    use crate::{database::DatabaseManager, entities::Team, testing::{database, team}};
+    use super::*;
+
+    /// The team and its characters as saved
+    fn saved(database: &DatabaseManager, id: EntityID<Team>) -> Vec<Value> {
+        let team: Team = database.load(id);
+        let characters = team.roster.iter().map(|character| serde_json::to_value(database.load::<_, Character>(*character)).unwrap());
+        [serde_json::to_value(&team).unwrap()].into_iter().chain(characters).collect()
+    }
+
+    #[test]
+    fn settling_twice_changes_nothing_the_second_time() {
+        let database = database("settle-twice");
+        let (crabs, pirates) = (team(&database, "Crab"), team(&database, "Pirate"));
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut sim = Sim::new(database, &mut rng, crabs, pirates, None);
+        sim.log_events = false;
+        while !sim.finished() {
+            sim.turn();
+        }
+
+        sim.settle().unwrap();
+        let settled = [crabs, pirates].map(|team| saved(&sim.database, team));
+        sim.settle().unwrap();
+        assert_eq!([crabs, pirates].map(|team| saved(&sim.database, team)), settled);
+
+        for id in [crabs, pirates] {
+            let team: Team = sim.database.load(id);
+            let downs: u32 = team.roster.iter().map(|character| sim.database.load::<_, Character>(*character).career.downs).sum();
+            assert_eq!(team.career.downs, downs);
+            assert_eq!(team.career.games, 1);
+        }
+    }
+}
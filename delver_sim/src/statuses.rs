@@ -0,0 +1,43 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{events::Event, game::ActiveCharacter, progress_bars::{GameProgressBarLocation, ProgressBarName}};
+
+/// Temporary conditions on an active character. Unlike modifiers, these come and go during a game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Status {
+    /// Takes damage at the start of each of their turns
+    Poisoned,
+    /// Loses their next turn
+    Stunned
+}
+impl Status {
+    /// Stuns wear off with any rest, but only a full rest gets the poison out
+    pub fn cured_by_rest(&self, full: bool) -> bool {
+        match self {
+            Status::Poisoned => full,
+            Status::Stunned => true
+        }
+    }
+    pub fn start_turn(&self, character:&ActiveCharacter) -> Vec<Event> {
+        let mut events = Vec::new();
+        match self {
+            Status::Poisoned => {
+                events.push(Event::ProgressProgressBar { location: GameProgressBarLocation::Character(character.id, ProgressBarName::HP), amount: 1 })
+            }
+            Status::Stunned => {
+                events.push(Event::ClearStatus { character: character.id, status: *self })
+            }
+        }
+        events
+    }
+}
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(match self {
+            Status::Poisoned => "poisoned",
+            Status::Stunned => "stunned",
+        }, f)
+    }
+}
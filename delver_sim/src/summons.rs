@@ -0,0 +1,37 @@
+use chronobase::EntityID;
+use serde::{Deserialize, Serialize};
+
+use crate::{entities::Monster, events::Event, game::Game};
+
+/// What a summon is made from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummonKind {
+    /// A minion made in the image of the monster, without being the monster itself
+    Minion(EntityID<Monster>),
+    /// A copy of the summoner, as they are right now
+    Clone
+}
+
+/// A summon in play. Nothing backs it in the database, so its active character's profile is all there is of it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Summon {
+    /// Turns left before it fades away. None to last until the end of the game.
+    pub turns_left: Option<u8>
+}
+
+impl Game {
+    /// Counts down every summon still in play, fading away any that have run out of turns
+    pub(crate) fn expire_summons(&mut self) -> Vec<Event> {
+        let in_play: Vec<_> = self.turn_order.iter()
+            .flat_map(|team| self.rosters[team].characters().map(|(_, character)| character))
+            .collect();
+        in_play.into_iter()
+            .filter(|character| {
+                let Some(turns_left) = self.summons.get_mut(character).and_then(|summon| summon.turns_left.as_mut()) else { return false };
+                *turns_left = turns_left.saturating_sub(1);
+                *turns_left == 0
+            })
+            .map(|character| Event::Expire { character })
+            .collect()
+    }
+}
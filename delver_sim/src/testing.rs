@@ -0,0 +1,40 @@
+//! Setting up saved teams for tests to play games with
+
+use chronobase::{DirectConnection, EntityID};
+
+use crate::{database::DatabaseManager, entities::{Character, Stats, Team}, progress_bars::Colour};
+
+fn connection(name: &str) -> DirectConnection {
+    let path = std::env::temp_dir().join(format!("delver-{name}-test.db")).to_string_lossy().to_string();
+    DirectConnection::new(path)
+}
+
+/// An empty database of its own for the test
+pub fn database(name: &str) -> DatabaseManager {
+    let connection = connection(name);
+    connection.wipe().unwrap();
+    DatabaseManager::new(Box::new(connection))
+}
+/// The test's database again, as it was left
+pub fn reopen(name: &str) -> DatabaseManager {
+    DatabaseManager::new(Box::new(connection(name)))
+}
+
+/// Saves a team of four, each character made by `character` from their name
+pub fn team_of(database: &DatabaseManager, name: &str, character: impl Fn(String, EntityID<Team>) -> Character) -> EntityID<Team> {
+    let mut team = Team::new(name.to_string(), Colour::Red);
+    team.roster = (0..4).map(|i| {
+        let character = character(format!("{name} {i}"), team.id);
+        let id = character.id;
+        database.save(character);
+        id
+    }).collect();
+    let id = team.id;
+    database.save(team);
+    id
+}
+
+/// Saves a team of four with the example stats
+pub fn team(database: &DatabaseManager, name: &str) -> EntityID<Team> {
+    team_of(database, name, |name, team| Character::roll(name, Stats::example(), team))
+}
@@ -0,0 +1,41 @@
+use chronobase::EntityID;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{database::DatabaseManager, delve::Delve, game::{Game, Sim}, records::{GameMode, GameRecord}};
+
+impl<'a> Sim<'a> {
+    /// Picks a stored game up as it was at the end of the given turn, as a new game that remembers where it came from.
+    /// Whatever is done to the sim before its first turn, like swapping characters or handing out modifiers, is where the new timeline starts from,
+    /// and the rng it's given decides how it plays out from there. None if the game was never saved.
+    pub fn fork(database: DatabaseManager, rng: &'a mut ChaCha8Rng, game: EntityID<Game>, turn: u32) -> Option<Self> {
+        let mut world = Game::reconstruct(&database, game, turn)?;
+        // The turn may be from before the game was forked itself, in which case it's the original's
+        let source = world.game_id;
+        world.game_id = EntityID::roll();
+        world.log = EntityID::roll();
+        world.started_at = world.turn;
+        world.forked_from = Some((game, world.turn));
+        world.latest_events.clear();
+        world.turn_events = 0;
+        world.settled = false;
+        world.record = GameRecord::starting(world.record.mode, world.record.day);
+
+        // Plays the way the original would have, retreating from a delve encounter and all, on a copy of the delve as it was going in,
+        // so the original delve isn't moved on
+        let delve = world.record.mode.delve()
+            .and_then(|delve| database.history::<Delve>(delve).into_iter().rev().find(|version| !version.games.contains(&source)))
+            .map(|delve| Delve { id: EntityID::roll(), ..delve });
+        if let Some(delve) = &delve {
+            world.record.mode = GameMode::Delve(delve.id);
+            database.save(delve.clone());
+        }
+        Some(Sim {
+            database,
+            rng,
+            world,
+            delve,
+            log_events: true,
+            save_turns: true
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use chronobase::{EntityID, SavableEntity};
+use serde::{Deserialize, Serialize};
+
+use crate::{abilities::Ability, database::DatabaseManager, entities::{Combatant, Profile}, events::CompletedEvent, game::{Game, Sim, RULES_VERSION}};
+
+/// How many turns go by between full snapshots of the game. Anything in between is rebuilt from the log.
+pub const SNAPSHOT_INTERVAL: u32 = 10;
+
+/// Everything that happened in one turn of a game. Each turn is saved as a new version of the game's log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TurnLog {
+    pub id: EntityID<TurnLog>,
+    pub game: EntityID<Game>,
+    pub turn: u32,
+    /// Done between the turn before and this one, such as swapping positions. Played before the turn.
+    #[serde(default)]
+    pub between: Vec<CompletedEvent>,
+    pub events: Vec<CompletedEvent>,
+    /// The game's `state_hash` at the end of the turn
+    pub state_hash: u64
+}
+impl TurnLog {
+    /// The turn the game has just finished, after whatever was done between it and the turn before
+    pub fn of(world: &Game, between: Vec<CompletedEvent>) -> Self {
+        let events = world.latest_events[..world.turn_events].to_vec();
+        Self { id: world.log, game: world.game_id, turn: world.turn, between, events, state_hash: world.state_hash() }
+    }
+}
+
+impl SavableEntity for TurnLog {
+    const TABLE_NAME: &'static str = "turn_log";
+}
+
+impl Sim<'_> {
+    /// Notes down everything needed to play the game again exactly, then saves it as the state the log is replayed on top of
+    pub(crate) fn record_start(&mut self) {
+        self.world.seed = self.rng.get_seed();
+        self.world.stream_position = self.rng.get_word_pos();
+        self.world.rules_version = RULES_VERSION;
+        // Whatever was done setting the game up is part of the start
+        self.world.turn_events = self.world.latest_events.len();
+        self.start_record();
+        self.pin_monsters();
+        self.database.save(self.world.clone());
+    }
+    /// Loads every monster that could join partway through the game, so later edits to them don't change it
+    fn pin_monsters(&mut self) {
+        let mut pending: Vec<_> = self.world.boss.iter().flat_map(|fight| fight.phases.iter().flat_map(|phase| {
+            phase.adds.iter().copied().chain(phase.abilities.iter().flat_map(Ability::minions))
+        })).collect();
+        pending.extend(self.world.active_characters.0.values().flat_map(|active_character| active_character.profile.abilities.iter().flat_map(Ability::minions)));
+        while let Some(monster) = pending.pop() {
+            if self.world.monsters.contains_key(&monster) {
+                continue
+            }
+            let profile: Profile = self.database.load(Combatant::Monster(monster));
+            pending.extend(profile.abilities.iter().flat_map(Ability::minions));
+            self.world.monsters.insert(monster, profile);
+        }
+    }
+    /// Saves the turn that just finished to the log, and a snapshot of the game if one is due
+    pub(crate) fn record_turn(&mut self, between: Vec<CompletedEvent>) {
+        self.database.save(TurnLog::of(&self.world, between));
+        if self.world.turn.is_multiple_of(SNAPSHOT_INTERVAL) {
+            self.database.save(self.world.clone());
+        }
+    }
+}
+
+impl Game {
+    /// Rebuilds the game as it was at the end of the given turn, from the last snapshot before it and the turns logged since.
+    /// Turn 0 is the game as it started. None if the game was never saved.
+    pub fn reconstruct(database: &DatabaseManager, game: EntityID<Game>, turn: u32) -> Option<Game> {
+        let snapshots: Vec<Game> = database.history(game);
+        let Some(mut world) = snapshots.iter().rev().find(|snapshot| snapshot.turn <= turn).cloned() else {
+            // Up to the turn it was forked at, a fork is still its parent
+            let (parent, _) = snapshots.first()?.forked_from?;
+            return Game::reconstruct(database, parent, turn)
+        };
+        let (snapshot, logged): (_, Vec<TurnLog>) = (world.turn, database.history(world.log));
+        for log in logged.iter().filter(|log| snapshot < log.turn && log.turn <= turn) {
+            world.replay_turn(log, database);
+        }
+        Some(world)
+    }
+    /// Plays the logged turn over again on top of this game, which should be as it was at the end of the turn before
+    pub fn replay_turn(&mut self, log: &TurnLog, database: &DatabaseManager) {
+        for event in log.between.iter().chain(&log.events) {
+            event.replay(self, database);
+        }
+        self.latest_events = log.events.clone();
+        self.turn_events = log.events.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{game::Position, modifiers::ModifierType, testing::{database, team}};
+
+    #[test]
+    fn reconstruct_includes_what_was_done_between_turns() {
+        let database = database("reconstruct");
+        let (crabs, pirates) = (team(&database, "Crab"), team(&database, "Pirate"));
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut sim = Sim::new(database, &mut rng, crabs, pirates, None);
+        sim.log_events = false;
+
+        sim.turn();
+        sim.swap_positions(crabs, Position::Top, Position::Up);
+        let pirate = sim.world.rosters[&pirates].characters().next().unwrap().1;
+        sim.add_modifier(pirate, ModifierType::Grinder, false);
+        sim.turn();
+
+        let game = sim.world.game_id;
+        let rebuilt = Game::reconstruct(&sim.database, game, 2).unwrap();
+        let roster = |world: &Game| world.rosters[&crabs].characters().collect::<Vec<_>>();
+        assert_eq!(roster(&rebuilt), roster(&sim.world));
+        assert_eq!(rebuilt.state_hash(), sim.world.state_hash());
+        assert!(sim.divergence(game).is_none());
+    }
+}
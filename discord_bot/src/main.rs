@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use delver_sim::{database::DatabaseManager, delver_display::{DisplayConstruct, ToDisplayConstruct}, game::Game};
+use delver_sim::{database::DatabaseManager, delver_display::{DisplayConstruct, ToDisplayConstruct}, entities::Team, game::Game, turn_log::TurnLog};
 use poise::serenity_prelude::{self as serenity, CreateMessage, MessageBuilder};
 use chronobase::{DirectConnection, EntityID};
 use tokio::time::sleep;
@@ -29,16 +29,57 @@ async fn print_out_game(
     game_id: Uuid,
     n: usize
 ) -> Result<(), Error> {
-    let mut game = ctx.data().0.0.load(EntityID::<Game>::from(game_id), None, n)?;
-    game.reverse();
-    for state in game {
-        let events = DisplayConstruct::Multi(state.latest_events.iter().map(|event| event.longform(&state, &ctx.data().0)).collect());
+    let database = &ctx.data().0;
+    let game: Game = database.try_load(EntityID::<Game>::from(game_id)).ok_or("No such game")?;
+    display_construct(ctx, game.record.longform(&game, database)).await?;
+    let logged: Vec<TurnLog> = database.history(game.log);
+    // The latest snapshot can be a few turns behind the log
+    let last = logged.last().map_or(game.turn, |log| log.turn);
+    let first = last.saturating_sub(n as u32).max(game.started_at);
+    let mut state = Game::reconstruct(database, game.game_id, first).ok_or("No such game")?;
+    for log in logged.iter().filter(|log| first < log.turn) {
+        state.replay_turn(log, database);
+        let events = DisplayConstruct::Multi(state.latest_events.iter().map(|event| event.longform(&state, database)).collect());
         display_construct(ctx, events).await?;
         sleep(Duration::from_millis(500)).await;
     }
     Ok(())
 }
 
+/// The most recent games, or the most recent a team played in
+#[poise::command(slash_command, prefix_command)]
+async fn list_games(
+    ctx: Context<'_>,
+    team: Option<String>,
+    n: usize
+) -> Result<(), Error> {
+    let database = &ctx.data().0;
+    let games = match team {
+        Some(team) => match database.teams_named(&team).first() {
+            Some(team) => database.team_games(*team).into_iter().rev().take(n).collect(),
+            None => {
+                let suggestions: Vec<String> = database.search_teams(&team).into_iter().take(5)
+                    .filter_map(|team| database.try_load(team))
+                    .map(|team: Team| team.name)
+                    .collect();
+                if suggestions.is_empty() {
+                    return Err("No team goes by that name".into())
+                }
+                let message = format!("No team goes by that name. Did you mean {}?", suggestions.join(", "));
+                ctx.say(message).await?;
+                return Ok(())
+            }
+        },
+        None => database.recent_games(n)
+    };
+    // Listings are shown outside of any game
+    let game = Game::default();
+    let listed = games.iter()
+        .map(|listing| format!("{} ({})", listing.record.shortform(&game, database), listing.game))
+        .collect();
+    display_construct(ctx, DisplayConstruct::List(listed)).await
+}
+
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
@@ -48,7 +89,7 @@ async fn main() {
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![print_out_game()],
+            commands: vec![print_out_game(), list_games()],
             ..Default::default()
         })
         .setup(|ctx, ready, framework| {
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+use delver_sim::{database::DatabaseManager, entities::{Character, Stats, Team}, game::{Game, IdSource, Sim}, progress_bars::Colour, records::{GameMode, GameRecord}};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use chronobase::{DirectConnection, EntityID};
+
+const TEAMS: usize = 12;
+const GAMES: usize = 60;
+const TURNS: usize = 5;
+
+fn build_teams(database: &DatabaseManager) -> Vec<EntityID<Team>> {
+    (0..TEAMS).map(|i| {
+        let mut team = Team::new(format!("Team {i}"), Colour::Blue);
+        let roster: Vec<_> = (0..6).map(|j| Character::roll(format!("Delver {i}-{j}"), Stats::example(), team.id)).collect();
+        team.roster = roster.iter().map(|character| character.id).collect();
+        roster.into_iter().for_each(|character| database.save(character));
+        let id = team.id;
+        database.save(team);
+        id
+    }).collect()
+}
+
+/// Plays short games between neighbouring teams. Turns don't load anything now that profiles are carried on the game,
+/// so only setting each game up and settling it, which load every character and team taking part, is timed. Nothing is rendered.
+fn run(database: DatabaseManager) -> Duration {
+    let teams = build_teams(&database);
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+    let mut sim = Sim { database, rng: &mut rng, world: Game::default(), delve: None, log_events: false, save_turns: true };
+    let mut elapsed = Duration::ZERO;
+    for game in 0..GAMES {
+        // Set up as `Sim::new` would, but without printing each character as they're added
+        let start = Instant::now();
+        sim.world = Game::default();
+        sim.world.record = GameRecord::starting(GameMode::Arena, None);
+        sim.world.ids = IdSource::seeded(sim.rng);
+        sim.add_team(teams[game % TEAMS]);
+        sim.add_team(teams[(game + 1) % TEAMS]);
+        elapsed += start.elapsed();
+        for _ in 0..TURNS {
+            if sim.finished() {
+                break
+            }
+            sim.turn();
+        }
+        let start = Instant::now();
+        sim.settle().expect("The game should save");
+        elapsed += start.elapsed();
+    }
+    elapsed
+}
+
+/// A database of its own for each run, so neither works over what the other left behind
+fn fresh_database(name: &str) -> DirectConnection {
+    let connection = DirectConnection::new(format!("bench-{name}.db"));
+    connection.wipe().unwrap();
+    connection
+}
+
+fn main() {
+    for (name, database) in [
+        ("uncached", DatabaseManager::new(Box::new(fresh_database("uncached")))),
+        ("cached", DatabaseManager::cached(Box::new(fresh_database("cached")))),
+    ] {
+        let elapsed = run(database);
+        println!("{name}: {GAMES} games set up and settled in {elapsed:?} ({:.1} games/s)", GAMES as f64 / elapsed.as_secs_f64());
+    }
+}
@@ -0,0 +1,100 @@
+use delver_sim::{abilities::{Ability, Effect, Targeting}, bosses::{Boss, Phase}, database::DatabaseManager, delve::Delve, dungeon::{Dungeon, DungeonSettings}, delver_display::ToDisplayConstruct, entities::{Character, Monster, Stat, Stats, Team}, game::Sim, items::{Consumable, Item, ItemTemplate, Slot}, loot::LootTable, modifiers::{Modifier, ModifierType}, progress_bars::{Colour, ProgressBarName}, records::GameDay, summons::SummonKind};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use chronobase::{DirectConnection, EntityID};
+
+fn monster(database: &DatabaseManager, name: &str, colour: Colour, loot_table: LootTable) -> EntityID<Monster> {
+    let mut monster = Monster::new(name.to_string(), colour, Stats::example());
+    monster.loot_table = loot_table;
+    let id = monster.id;
+    database.save(monster);
+    id
+}
+
+fn main() {
+    // Initialise Database
+    let database = DirectConnection::new("temp.db".to_string());
+    let database= DatabaseManager::cached(Box::new(database));
+
+    let mut crabs = Team::new("Baltimore Crabs".to_string(), Colour::Red);
+    let crab_team_id = crabs.id;
+
+    let mut crabs_roster:Vec<_> = (0..6).map(|i| Character::roll(format!("Crab {}", i), Stats::example(), crabs.id)).collect();
+    let crabs_roster_ids: Vec<_> = crabs_roster.iter().map(|character| character.id).collect();
+    crabs_roster[0].modifiers.push(Modifier::new(crabs_roster_ids[0], ModifierType::Grinder));
+    crabs_roster[1].modifiers.push(Modifier::new(crabs_roster_ids[1], ModifierType::Resilient));
+    crabs_roster[2].modifiers.push(Modifier::new(crabs_roster_ids[2], ModifierType::Counterattack));
+    crabs_roster[3].modifiers.push(Modifier::new(crabs_roster_ids[3], ModifierType::Cover));
+    crabs_roster[4].abilities.push(Ability::new("Snap".to_string(), Targeting::Enemy, vec![Effect::Damage(3)], 2, Some((ProgressBarName::Energy, 2))));
+    crabs_roster[0].abilities.push(Ability::new("Molt".to_string(), Targeting::User, vec![Effect::Summon(SummonKind::Clone, 3)], 5, None));
+    crabs_roster[5].abilities.push(Ability::new("Mend".to_string(), Targeting::Ally, vec![Effect::Heal(2)], 3, None));
+    let cutlass = Item::new(ItemTemplate::equipment("Rusty Cutlass".to_string(), Slot::Weapon, vec![(Stat::Violence, 2)], Vec::new()), Some(crab_team_id));
+    let shell = Item::new(ItemTemplate::equipment("Hermit Shell".to_string(), Slot::Armour, vec![(Stat::Run, -1)], vec![ModifierType::Resilient]), Some(crab_team_id));
+    crabs_roster[2].equip(&cutlass);
+    crabs_roster[3].equip(&shell);
+    crabs.inventory = vec![cutlass.id, shell.id];
+    database.save(cutlass);
+    database.save(shell);
+    let consumables = [
+        ItemTemplate::consumable("Kelp Tonic".to_string(), Consumable::Potion { healing: 3 }),
+        ItemTemplate::consumable("Blowfish Bomb".to_string(), Consumable::Bomb { damage: 2 }),
+        ItemTemplate::consumable("Tide Scroll".to_string(), Consumable::Scroll),
+    ];
+    for template in consumables {
+        let item = Item::new(template, Some(crab_team_id));
+        crabs.inventory.push(item.id);
+        database.save(item);
+    }
+    crabs_roster.into_iter().for_each(|character| database.save(character));
+    crabs.roster = crabs_roster_ids;
+    database.save(crabs);
+
+    let mut scraps = LootTable::default();
+    scraps.add(1, ItemTemplate::new("Eel Skin".to_string()));
+    scraps.add_nothing(2);
+    let mut hoard = LootTable::default();
+    hoard.add(3, ItemTemplate::new("Tarnished Coin".to_string()));
+    hoard.add(1, ItemTemplate::new("Pearl".to_string()));
+
+    let eel = monster(&database, "Eel", Colour::Green, scraps);
+    let leviathan = Boss {
+        monster: monster(&database, "Leviathan", Colour::Pink, hoard.clone()),
+        hp: 12,
+        phases: vec![
+            Phase { name: "Enraged".to_string(), threshold: 4, adds: vec![eel, eel], modifiers: Vec::new(), abilities: Vec::new() },
+            Phase {
+                name: "Desperate".to_string(),
+                threshold: 8,
+                adds: Vec::new(),
+                modifiers: vec![ModifierType::Resilient],
+                abilities: vec![Ability::new("Tidal Crash".to_string(), Targeting::EnemyTeam, vec![Effect::Damage(2)], 3, None)]
+            },
+        ]
+    };
+
+    let settings = DungeonSettings {
+        monsters: vec![
+            monster(&database, "Rat", Colour::Gray, LootTable::default()),
+            eel,
+            monster(&database, "Angler", Colour::Blue, LootTable::default()),
+        ],
+        bosses: vec![leviathan],
+        treasure: hoard,
+        ..Default::default()
+    };
+
+    // Initialisation
+    let mut rng = rand::thread_rng();
+    let dungeon = Dungeon::generate(rng.gen(), &settings).expect("The settings should be valid");
+    database.save(dungeon.clone());
+    let delve = Delve::from_dungeon(crab_team_id, &dungeon);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(rng.gen());
+
+    let mut sim = Sim::new_delve(database, &mut rng, delve, Some(GameDay { season: 1, day: 2 }));
+    sim.run_delve().expect("The delve should save");
+
+    let delve = sim.delve.as_ref().unwrap();
+    println!("{}", delve.id);
+    println!("{}", delve.longform(&sim.world, &sim.database));
+}
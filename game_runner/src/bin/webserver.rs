@@ -1,5 +1,5 @@
 use chronobase::DirectConnection;
-use delver_sim::entities::{Character, Team};
+use delver_sim::{delve::Delve, dungeon::Dungeon, entities::{Character, Monster, Team}, game::Game, items::Item, names::NameIndex, records::GameListing, turn_log::TurnLog};
 
 const DATABASE_PATH:&str = "DelverBase.db";
 
@@ -10,5 +10,13 @@ async fn main () {
     chronobase::webserver::build(connection)
     .add_table::<Character>()
     .add_table::<Team>()
+    .add_table::<Delve>()
+    .add_table::<Dungeon>()
+    .add_table::<Monster>()
+    .add_table::<Item>()
+    .add_table::<Game>()
+    .add_table::<TurnLog>()
+    .add_table::<GameListing>()
+    .add_table::<NameIndex>()
     .run_server().await
 }
\ No newline at end of file
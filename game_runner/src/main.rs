@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use delver_sim::{database::DatabaseManager, delver_display::ToDisplayConstruct, entities::{Character, Stats, Team}, game::Sim, modifiers::{Modifier, ModifierType}, progress_bars::Colour};
+use delver_sim::{database::DatabaseManager, delver_display::ToDisplayConstruct, entities::{Character, Stats, Team}, game::Sim, modifiers::{Modifier, ModifierType}, progress_bars::Colour, records::GameDay};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use chronobase::{DirectConnection, HTTPConnection};
@@ -7,7 +7,7 @@ use chronobase::{DirectConnection, HTTPConnection};
 fn main() {
     // Initialise Database
     let database = DirectConnection::new("temp.db".to_string());
-    let database= DatabaseManager::new(Box::new(database));
+    let database= DatabaseManager::cached(Box::new(database));
     // let database = HTTPConnection::new("http://localhost:8000");
 
     let mut crabs = Team::new("Baltimore Crabs".to_string(), Colour::Red);
@@ -49,12 +49,31 @@ fn main() {
     let mut rng = ChaCha8Rng::seed_from_u64(rng.gen());
 
 
-    let mut game = Sim::new(database, &mut rng, crab_team_id, pirate_team_id);
+    let mut game = Sim::new(database, &mut rng, crab_team_id, pirate_team_id, Some(GameDay { season: 1, day: 1 }));
     game.display(crab_team_id, pirate_team_id);
     for _ in 0..10 {
         // game.display(crab_team_id, pirate_team_id);
+        if game.finished() {
+            break;
+        }
         game.turn();
     }
+    game.settle().expect("The game should save");
     println!("{}", game.world.game_id);
+    println!("{}", game.world.record.longform(&game.world, &game.database));
+    // The seed is saved with the game, so it should play out the same way again
+    game.replay(game.world.game_id);
     println!("{}", clawed_one.longform(&game.world, &game.database));
+
+    // What if the first crab still standing at turn 5 had been tougher from then on?
+    let (game_id, database) = (game.world.game_id, game.database);
+    let mut timeline_rng = ChaCha8Rng::seed_from_u64(rng.gen());
+    let mut timeline = Sim::fork(database, &mut timeline_rng, game_id, 5).expect("The game was just saved");
+    let crab = timeline.world.rosters[&crab_team_id].characters().next().map(|(_, crab)| crab);
+    if let Some(crab) = crab {
+        timeline.add_modifier(crab, ModifierType::Resilient, false);
+    }
+    while timeline.world.turn < 10 && !timeline.finished() {
+        timeline.turn();
+    }
 }
\ No newline at end of file